@@ -0,0 +1,531 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    failure::PassedOut,
+    fella::{BasicMotives, Fella, Named, WalkTarget},
+    object::InteractableObject,
+    pathfinding::RouteFailed,
+    time::{advance_time, SimulationTick, SimulationTime},
+    utility::FellaAction,
+    world::{TilePosition, WorldPosition},
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ActionState {
+    // Waiting for its turn, or for an interaction slot to free up
+    Queued,
+    WalkingTo {
+        slot: usize,
+    },
+    Performing {
+        slot: usize,
+        started_at: SimulationTime,
+    },
+}
+
+impl ActionState {
+    // Interaction slot of the object the fella is headed for or using
+    pub fn slot(&self) -> Option<usize> {
+        match *self {
+            ActionState::Queued => None,
+            ActionState::WalkingTo { slot } | ActionState::Performing { slot, .. } => Some(slot),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActionSource {
+    // The fella came up with it on their own
+    Autonomous,
+    // Ordered by the player
+    Player,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CancelReason {
+    // Cancelled on purpose, e.g. by the player
+    Cancelled,
+    // Pushed aside by something more urgent
+    Interrupted,
+    // The object is gone
+    Unavailable,
+    // There's no way to get to the object
+    Unreachable,
+}
+
+#[derive(Clone, Debug)]
+pub struct QueuedAction {
+    pub action: FellaAction,
+    pub state: ActionState,
+    pub source: ActionSource,
+    cancel_reason: Option<CancelReason>,
+}
+
+impl QueuedAction {
+    pub fn new(action: FellaAction, source: ActionSource) -> Self {
+        QueuedAction {
+            action,
+            state: ActionState::Queued,
+            source,
+            cancel_reason: None,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_reason.is_some()
+    }
+
+    pub fn slot(&self) -> Option<usize> {
+        self.state.slot()
+    }
+
+    // How far along the action is, from 0.0 to 1.0
+    pub fn progress(&self, time: &SimulationTime) -> f32 {
+        let ActionState::Performing { started_at, .. } = &self.state else {
+            return 0.0;
+        };
+
+        let duration = self.action.object_action().duration.max(1);
+        (time.time_since_ticks(started_at) as f32 / duration as f32).min(1.0)
+    }
+}
+
+// Everything a fella is going to do, in order. The first action is the one in progress.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ActionQueue(VecDeque<QueuedAction>);
+
+impl ActionQueue {
+    pub fn push(&mut self, action: FellaAction) {
+        self.0
+            .push_back(QueuedAction::new(action, ActionSource::Autonomous));
+    }
+
+    // Player commands override anything the fella decided to do on their own
+    pub fn command(&mut self, action: FellaAction) {
+        for queued in self.0.iter_mut() {
+            if queued.source == ActionSource::Autonomous {
+                queued.cancel_reason.get_or_insert(CancelReason::Cancelled);
+            }
+        }
+
+        self.0
+            .push_back(QueuedAction::new(action, ActionSource::Player));
+    }
+
+    // Drops whatever the fella is doing in favour of the given action
+    pub fn interrupt(&mut self, action: FellaAction) {
+        self.cancel(0, CancelReason::Interrupted);
+        self.0
+            .push_front(QueuedAction::new(action, ActionSource::Autonomous));
+    }
+
+    // Index is the same as in iter(), i.e. ignoring already cancelled actions.
+    // The action is removed (and ActionCancelled sent) when the queue is next advanced.
+    pub fn cancel(&mut self, index: usize, reason: CancelReason) {
+        let queued = self
+            .0
+            .iter_mut()
+            .filter(|queued| !queued.is_cancelled())
+            .nth(index);
+
+        if let Some(queued) = queued {
+            queued.cancel_reason = Some(reason);
+        }
+    }
+
+    pub fn cancel_all(&mut self, reason: CancelReason) {
+        for queued in self.0.iter_mut() {
+            queued.cancel_reason.get_or_insert(reason);
+        }
+    }
+
+    pub fn current(&self) -> Option<&QueuedAction> {
+        self.iter().next()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &QueuedAction> {
+        self.0.iter().filter(|queued| !queued.is_cancelled())
+    }
+}
+
+impl FromIterator<QueuedAction> for ActionQueue {
+    fn from_iter<T: IntoIterator<Item = QueuedAction>>(iter: T) -> Self {
+        ActionQueue(iter.into_iter().collect())
+    }
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct ActionStarted {
+    pub fella: Entity,
+    pub action: FellaAction,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct ActionCompleted {
+    pub fella: Entity,
+    pub action: FellaAction,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct ActionCancelled {
+    pub fella: Entity,
+    pub action: FellaAction,
+    pub reason: CancelReason,
+}
+
+// Counts how many fellas are currently walking to or using each object
+pub fn count_object_claims<'a>(
+    queues: impl Iterator<Item = &'a ActionQueue>,
+) -> HashMap<Entity, usize> {
+    let mut claims = HashMap::new();
+
+    for queue in queues {
+        if let Some(current) = queue.current() {
+            *claims.entry(current.action.object()).or_insert(0) += 1;
+        }
+    }
+
+    claims
+}
+
+// Tiles of interaction slots that fellas are walking to or standing on,
+// so that no two fellas try to use the same spot at once
+#[derive(Resource, Clone, Debug, Default)]
+pub struct TileReservations(pub HashMap<TilePosition, Entity>);
+
+impl TileReservations {
+    pub fn is_reserved_for_someone_else(&self, tile: TilePosition, fella: Entity) -> bool {
+        self.0
+            .get(&tile)
+            .is_some_and(|&reserved_by| reserved_by != fella)
+    }
+
+    fn release(&mut self, tile: TilePosition, fella: Entity) {
+        if self.0.get(&tile) == Some(&fella) {
+            self.0.remove(&tile);
+        }
+    }
+}
+
+fn slot_tile(
+    objects: &Query<(&InteractableObject, &WorldPosition)>,
+    queued: &QueuedAction,
+) -> Option<TilePosition> {
+    let slot = queued.slot()?;
+    let (object, object_position) = objects.get(queued.action.object()).ok()?;
+    let slot_position = object.slot_position(object_position.0, slot);
+
+    Some(TilePosition::from_world(&WorldPosition(slot_position)))
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn advance_action_queues(
+    time: Res<SimulationTime>,
+    objects: Query<(&InteractableObject, &WorldPosition)>,
+    mut fellas: Query<
+        (
+            Entity,
+            &mut ActionQueue,
+            &mut WalkTarget,
+            &WorldPosition,
+            Has<PassedOut>,
+        ),
+        With<Fella>,
+    >,
+    mut route_failed_events: EventReader<RouteFailed>,
+    mut started_events: EventWriter<ActionStarted>,
+    mut completed_events: EventWriter<ActionCompleted>,
+    mut cancelled_events: EventWriter<ActionCancelled>,
+    mut reservations: ResMut<TileReservations>,
+) {
+    // Give up on walking to objects that can't be reached
+    for event in route_failed_events.read() {
+        let Ok((_, mut queue, walk_target, _, _)) = fellas.get_mut(event.fella) else {
+            continue;
+        };
+
+        let is_walking = matches!(
+            queue.current().map(|current| &current.state),
+            Some(ActionState::WalkingTo { .. })
+        );
+
+        if is_walking && walk_target.target == event.target {
+            queue.cancel(0, CancelReason::Unreachable);
+        }
+    }
+
+    // Rebuilt every time so that reservations can't outlive the actions that made them
+    reservations.0.clear();
+
+    for (fella, queue, _, _, _) in fellas.iter() {
+        for queued in queue.0.iter() {
+            if let Some(tile) = slot_tile(&objects, queued) {
+                reservations.0.insert(tile, fella);
+            }
+        }
+    }
+
+    // Queues are only borrowed mutably when something actually changes,
+    // so that the UI can rely on change detection
+    for (fella, mut queue, mut walk_target, world_position, passed_out) in fellas.iter_mut() {
+        // Objects can disappear, e.g. when their catalog is reloaded
+        let is_unavailable = |queued: &QueuedAction| objects.get(queued.action.object()).is_err();
+
+        if queue.iter().any(is_unavailable) {
+            for queued in queue.0.iter_mut() {
+                if is_unavailable(queued) {
+                    queued
+                        .cancel_reason
+                        .get_or_insert(CancelReason::Unavailable);
+                }
+            }
+        }
+
+        if queue.0.iter().any(QueuedAction::is_cancelled) {
+            queue.0.retain(|queued| {
+                let Some(reason) = queued.cancel_reason else {
+                    return true;
+                };
+
+                if let Some(tile) = slot_tile(&objects, queued) {
+                    reservations.release(tile, fella);
+                }
+
+                cancelled_events.send(ActionCancelled {
+                    fella,
+                    action: queued.action.clone(),
+                    reason,
+                });
+
+                false
+            });
+        }
+
+        // Cancelled actions still go as soon as they're cancelled, but nothing new gets
+        // going until the fella wakes up
+        if passed_out {
+            continue;
+        }
+
+        let Some(current) = queue.0.front() else {
+            continue;
+        };
+
+        let (object, object_position) = objects.get(current.action.object()).unwrap();
+
+        match current.state.clone() {
+            ActionState::Queued => {
+                let free_slot = (0..object.definition.capacity())
+                    .map(|slot| (slot, object.slot_position(object_position.0, slot)))
+                    .find(|(_, slot_position)| {
+                        let tile = TilePosition::from_world(&WorldPosition(*slot_position));
+                        !reservations.is_reserved_for_someone_else(tile, fella)
+                    });
+
+                // Everyone else got there first, so wait until someone's done
+                let Some((slot, slot_position)) = free_slot else {
+                    continue;
+                };
+
+                let tile = TilePosition::from_world(&WorldPosition(slot_position));
+                reservations.0.insert(tile, fella);
+                walk_target.target = slot_position;
+                walk_target.assigned_at = time.clone();
+                queue.0.front_mut().unwrap().state = ActionState::WalkingTo { slot };
+            }
+            ActionState::WalkingTo { slot } => {
+                // The object might have been moved in the meantime
+                let slot_position = object.slot_position(object_position.0, slot);
+
+                if walk_target.target != slot_position {
+                    walk_target.target = slot_position;
+                    continue;
+                }
+
+                if walk_target.target.distance(world_position.0) >= 0.1 {
+                    continue;
+                }
+
+                started_events.send(ActionStarted {
+                    fella,
+                    action: current.action.clone(),
+                });
+
+                queue.0.front_mut().unwrap().state = ActionState::Performing {
+                    slot,
+                    started_at: time.clone(),
+                };
+            }
+            ActionState::Performing { started_at, .. } => {
+                let duration = current.action.object_action().duration.max(1);
+
+                if time.time_since_ticks(&started_at) < duration {
+                    continue;
+                }
+
+                let finished = queue.0.pop_front().unwrap();
+
+                if let Some(tile) = slot_tile(&objects, &finished) {
+                    reservations.release(tile, fella);
+                }
+
+                completed_events.send(ActionCompleted {
+                    fella,
+                    action: finished.action,
+                });
+            }
+        }
+    }
+}
+
+fn perform_actions(mut fellas: Query<(&ActionQueue, &mut BasicMotives), With<Fella>>) {
+    for (queue, mut basic_motives) in fellas.iter_mut() {
+        let Some(current) = queue.current() else {
+            continue;
+        };
+
+        let ActionState::Performing { .. } = current.state else {
+            continue;
+        };
+
+        let action = current.action.object_action();
+        let duration = action.duration.max(1);
+
+        // Spread the motive changes evenly over the duration of the action
+        for &(motive, change) in action.motive_changes.iter() {
+            basic_motives.change(motive, change / duration as f32);
+        }
+    }
+}
+
+fn log_action_events(
+    fellas: Query<&Named>,
+    mut started_events: EventReader<ActionStarted>,
+    mut completed_events: EventReader<ActionCompleted>,
+    mut cancelled_events: EventReader<ActionCancelled>,
+) {
+    let name = |fella: Entity| fellas.get(fella).map_or("Someone", |name| name.0.as_str());
+
+    for event in started_events.read() {
+        println!("{} started: {}", name(event.fella), event.action);
+    }
+
+    for event in completed_events.read() {
+        println!("{} finished: {}", name(event.fella), event.action);
+    }
+
+    for event in cancelled_events.read() {
+        println!(
+            "{} stopped: {} ({:?})",
+            name(event.fella),
+            event.action,
+            event.reason
+        );
+    }
+}
+
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileReservations>()
+            .add_event::<ActionStarted>()
+            .add_event::<ActionCompleted>()
+            .add_event::<ActionCancelled>()
+            .add_systems(
+                SimulationTick,
+                (
+                    advance_action_queues,
+                    perform_actions.after(advance_action_queues),
+                    log_action_events.after(advance_action_queues),
+                )
+                    .after(advance_time),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        test_utils::{action, definition},
+        utility::ObjectDefinition,
+    };
+
+    fn use_bed(index: usize) -> FellaAction {
+        let bed = Arc::new(ObjectDefinition {
+            actions: vec![action("Sleep"), action("Nap")],
+            ..definition("Bed")
+        });
+
+        FellaAction::UseObject(Entity::from_raw(1), bed, index)
+    }
+
+    #[test]
+    fn interrupt_replaces_current_action() {
+        let mut queue = ActionQueue::default();
+        queue.push(use_bed(0));
+        queue.interrupt(use_bed(1));
+
+        assert_eq!(queue.current().unwrap().action, use_bed(1));
+        assert_eq!(queue.iter().count(), 1);
+    }
+
+    #[test]
+    fn cancelled_actions_are_skipped() {
+        let mut queue = ActionQueue::default();
+        queue.push(use_bed(0));
+        queue.push(use_bed(1));
+
+        queue.cancel(0, CancelReason::Cancelled);
+        assert_eq!(queue.current().unwrap().action, use_bed(1));
+
+        queue.cancel_all(CancelReason::Cancelled);
+        assert!(queue.current().is_none());
+    }
+
+    #[test]
+    fn commands_override_autonomous_actions() {
+        let mut queue = ActionQueue::default();
+        queue.push(use_bed(0));
+        queue.command(use_bed(1));
+
+        let current = queue.current().unwrap();
+        assert_eq!(current.action, use_bed(1));
+        assert_eq!(current.source, ActionSource::Player);
+        assert_eq!(queue.iter().count(), 1);
+    }
+
+    #[test]
+    fn progress_is_zero_until_performing() {
+        let time = SimulationTime::default();
+        let mut queue = ActionQueue::default();
+        queue.push(use_bed(0));
+
+        assert_eq!(queue.current().unwrap().progress(&time), 0.0);
+    }
+
+    #[test]
+    fn reserved_tiles_are_only_free_for_their_fella() {
+        let mut reservations = TileReservations::default();
+        let felix = Entity::from_raw(1);
+        let fiona = Entity::from_raw(2);
+        let toilet_slot = TilePosition::new(4, 1);
+
+        reservations.0.insert(toilet_slot, felix);
+
+        assert!(!reservations.is_reserved_for_someone_else(toilet_slot, felix));
+        assert!(reservations.is_reserved_for_someone_else(toilet_slot, fiona));
+
+        reservations.release(toilet_slot, fiona);
+        assert!(reservations.is_reserved_for_someone_else(toilet_slot, fiona));
+
+        reservations.release(toilet_slot, felix);
+        assert!(!reservations.is_reserved_for_someone_else(toilet_slot, fiona));
+    }
+}
@@ -0,0 +1,230 @@
+// How ticks add up to days, weeks, seasons and years, and how to write them down
+
+use bevy::prelude::*;
+
+use crate::time::SimulationTime;
+
+// A tick is always a minute, everything bigger is up to the calendar
+pub const MINUTES_PER_HOUR: u64 = 60;
+
+// Times of day like morning are picked for a 24 hour day, and stretched or squashed to fit
+const STANDARD_HOURS_PER_DAY: u64 = 24;
+const MORNING_HOUR: u64 = 7;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Season {
+    pub name: String,
+    pub days: u64,
+}
+
+impl Season {
+    pub fn new(name: &str, days: u64) -> Self {
+        Season {
+            name: name.to_string(),
+            days,
+        }
+    }
+}
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct Calendar {
+    pub hours_per_day: u64,
+    // The first day of the simulation is the first weekday
+    pub weekdays: Vec<String>,
+    // A year is all the seasons one after the other
+    pub seasons: Vec<Season>,
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Calendar {
+            hours_per_day: 24,
+            weekdays: [
+                "Monday",
+                "Tuesday",
+                "Wednesday",
+                "Thursday",
+                "Friday",
+                "Saturday",
+                "Sunday",
+            ]
+            .iter()
+            .map(|day| day.to_string())
+            .collect(),
+            seasons: vec![
+                Season::new("Spring", 90),
+                Season::new("Summer", 90),
+                Season::new("Autumn", 90),
+                Season::new("Winter", 90),
+            ],
+        }
+    }
+}
+
+impl Calendar {
+    pub fn ticks_per_day(&self) -> u64 {
+        self.hours_per_day * MINUTES_PER_HOUR
+    }
+
+    // How far through the day it is, from 0 at midnight up to 1 at the next one
+    pub fn day_fraction(&self, time: &SimulationTime) -> f32 {
+        time.get_time(self) as f32 / self.ticks_per_day() as f32
+    }
+
+    // Tick of the day that morning starts on, 07:00 on a 24 hour day
+    pub fn morning(&self) -> u64 {
+        self.ticks_per_day() * MORNING_HOUR / STANDARD_HOURS_PER_DAY
+    }
+
+    pub fn days_per_year(&self) -> u64 {
+        self.seasons
+            .iter()
+            .map(|season| season.days)
+            .sum::<u64>()
+            .max(1)
+    }
+
+    pub fn weekday_index(&self, time: &SimulationTime) -> usize {
+        (time.days(self) % self.weekdays.len().max(1) as u64) as usize
+    }
+
+    pub fn weekday(&self, time: &SimulationTime) -> &str {
+        self.weekdays
+            .get(self.weekday_index(time))
+            .map_or("", |day| day.as_str())
+    }
+
+    // The season and how many days into it we are, counting from 0
+    pub fn season(&self, time: &SimulationTime) -> Option<(&Season, u64)> {
+        let mut day = time.get_day(self);
+
+        for season in self.seasons.iter() {
+            if day < season.days {
+                return Some((season, day));
+            }
+
+            day -= season.days;
+        }
+
+        None
+    }
+
+    // From the start hour up to the end hour, wrapping around midnight if start > end.
+    // Working from 9 to 5 is `9, 17`, sleeping through the night `22, 6`.
+    pub fn is_between_hours(&self, time: &SimulationTime, start: u32, end: u32) -> bool {
+        let (hour, _) = time.get_hour_minute(self);
+
+        if start <= end {
+            (start..end).contains(&hour)
+        } else {
+            hour >= start || hour < end
+        }
+    }
+
+    pub fn is_on_weekdays(&self, time: &SimulationTime, weekdays: &[String]) -> bool {
+        weekdays.iter().any(|weekday| weekday == self.weekday(time))
+    }
+
+    // E.g. "Tuesday, Spring 2, Year 0", with days of the season counted from 1
+    pub fn format_date(&self, time: &SimulationTime) -> String {
+        let year = time.get_year(self);
+
+        match self.season(time) {
+            Some((season, day)) => format!(
+                "{}, {} {}, Year {}",
+                self.weekday(time),
+                season.name,
+                day + 1,
+                year
+            ),
+            None => format!(
+                "{}, Day {}, Year {}",
+                self.weekday(time),
+                time.get_day(self),
+                year
+            ),
+        }
+    }
+
+    // E.g. "09:30"
+    pub fn format_time(&self, time: &SimulationTime) -> String {
+        let (hour, minute) = time.get_hour_minute(self);
+        format!("{:02}:{:02}", hour, minute)
+    }
+
+    pub fn format(&self, time: &SimulationTime) -> String {
+        format!("{} {}", self.format_date(time), self.format_time(time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(days: u64, hour: u64, calendar: &Calendar) -> SimulationTime {
+        SimulationTime::from_ticks(days * calendar.ticks_per_day() + hour * MINUTES_PER_HOUR)
+    }
+
+    #[test]
+    fn default_calendar_has_weeks_and_seasons() {
+        let calendar = Calendar::default();
+        let time = at(92, 9, &calendar);
+
+        assert_eq!(calendar.days_per_year(), 360);
+        assert_eq!(calendar.weekday(&time), "Tuesday");
+        assert_eq!(calendar.format(&time), "Tuesday, Summer 3, Year 0 09:00");
+        assert_eq!(at(360, 0, &calendar).get_year(&calendar), 1);
+    }
+
+    #[test]
+    fn day_and_year_lengths_can_be_changed() {
+        let calendar = Calendar {
+            hours_per_day: 10,
+            weekdays: vec![String::from("Workday"), String::from("Restday")],
+            seasons: vec![Season::new("Wet", 3), Season::new("Dry", 2)],
+        };
+        let time = at(6, 4, &calendar);
+
+        assert_eq!(time.get_year(&calendar), 1);
+        assert_eq!(time.get_day(&calendar), 1);
+        assert_eq!(calendar.format(&time), "Workday, Wet 2, Year 1 04:00");
+    }
+
+    #[test]
+    fn working_hours_can_be_expressed() {
+        let calendar = Calendar::default();
+        let working_days: Vec<String> = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday"]
+            .iter()
+            .map(|day| day.to_string())
+            .collect();
+
+        assert!(calendar.is_between_hours(&at(0, 9, &calendar), 9, 17));
+        assert!(!calendar.is_between_hours(&at(0, 17, &calendar), 9, 17));
+        assert!(calendar.is_on_weekdays(&at(0, 9, &calendar), &working_days));
+        assert!(!calendar.is_on_weekdays(&at(5, 10, &calendar), &working_days));
+    }
+
+    #[test]
+    fn hours_can_wrap_around_midnight() {
+        let calendar = Calendar::default();
+
+        assert!(calendar.is_between_hours(&at(0, 23, &calendar), 22, 6));
+        assert!(calendar.is_between_hours(&at(1, 2, &calendar), 22, 6));
+        assert!(!calendar.is_between_hours(&at(1, 6, &calendar), 22, 6));
+    }
+
+    #[test]
+    fn morning_comes_however_short_the_days_are() {
+        assert_eq!(Calendar::default().morning(), 7 * MINUTES_PER_HOUR);
+
+        for hours_per_day in 1..=7 {
+            let calendar = Calendar {
+                hours_per_day,
+                ..default()
+            };
+
+            assert!(calendar.morning() > 0);
+            assert!(calendar.morning() < calendar.ticks_per_day());
+        }
+    }
+}
@@ -0,0 +1,288 @@
+// Object definitions authored as RON files in assets/objects
+
+use std::{collections::HashSet, sync::Arc};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadedFolder},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    object::create_object,
+    utility::ObjectDefinition,
+    world::{Direction, Edge, Floor, Rotation, TilePosition, WorldGrid},
+};
+
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct ObjectCatalog {
+    pub objects: Vec<Arc<ObjectDefinition>>,
+    // Objects to place in the world when the catalog is loaded
+    #[serde(default)]
+    pub placements: Vec<ObjectPlacement>,
+    // Walls and doors to build, see WorldGrid::add_wall
+    #[serde(default)]
+    pub walls: Vec<WallPlacement>,
+    #[serde(default)]
+    pub doors: Vec<DoorPlacement>,
+    // Anywhere not covered by one of these is grass
+    #[serde(default)]
+    pub floors: Vec<FloorPlacement>,
+}
+
+impl ObjectCatalog {
+    pub fn get(&self, name: &str) -> Option<&Arc<ObjectDefinition>> {
+        self.objects.iter().find(|object| object.name == name)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ObjectPlacement {
+    pub object: String,
+    pub position: Vec2,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WallPlacement {
+    pub from: IVec2,
+    pub to: IVec2,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DoorPlacement {
+    pub tile: IVec2,
+    pub side: Direction,
+}
+
+// Floor for every tile from one corner to the other, both included
+#[derive(Debug, Deserialize)]
+pub struct FloorPlacement {
+    pub from: IVec2,
+    pub to: IVec2,
+    pub floor: Floor,
+}
+
+impl FloorPlacement {
+    pub fn tiles(&self) -> impl Iterator<Item = TilePosition> {
+        let min = self.from.min(self.to);
+        let max = self.from.max(self.to);
+
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| TilePosition::new(x, y)))
+    }
+}
+
+#[derive(Default)]
+struct ObjectCatalogLoader;
+
+#[derive(Debug, Error)]
+enum ObjectCatalogLoaderError {
+    #[error("Could not read object catalog: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse object catalog: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for ObjectCatalogLoader {
+    type Asset = ObjectCatalog;
+    type Settings = ();
+    type Error = ObjectCatalogLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<ObjectCatalog, ObjectCatalogLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let catalog = ron::de::from_bytes::<ObjectCatalog>(&bytes)?;
+            Ok(catalog)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["objects.ron"]
+    }
+}
+
+// Keeps the catalogs loaded (and watched for changes)
+#[derive(Resource)]
+struct ObjectCatalogFolder(#[allow(dead_code)] Handle<LoadedFolder>);
+
+// Whether catalogs put down the objects listed in their placements. Once a save is loaded,
+// the save says where everything goes instead.
+#[derive(Resource)]
+pub struct PlaceCatalogObjects(pub bool);
+
+impl Default for PlaceCatalogObjects {
+    fn default() -> Self {
+        PlaceCatalogObjects(true)
+    }
+}
+
+// Marks objects placed by a catalog, so they can be replaced when it's reloaded.
+// Objects the player has moved are theirs, and lose the mark.
+#[derive(Component)]
+pub struct PlacedFromCatalog(pub AssetId<ObjectCatalog>);
+
+fn load_object_catalogs(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    catalogs: Res<Assets<ObjectCatalog>>,
+) {
+    // Catalogs added before startup, e.g. by tests, are used instead of the ones on disk
+    if !catalogs.is_empty() {
+        return;
+    }
+
+    commands.insert_resource(ObjectCatalogFolder(asset_server.load_folder("objects")));
+}
+
+fn place_catalog_objects(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    catalogs: Res<Assets<ObjectCatalog>>,
+    mut catalog_events: EventReader<AssetEvent<ObjectCatalog>>,
+    placed: Query<(Entity, &PlacedFromCatalog)>,
+    place_objects: Res<PlaceCatalogObjects>,
+    mut grid: ResMut<WorldGrid>,
+) {
+    let changed_catalogs: HashSet<AssetId<ObjectCatalog>> = catalog_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    if changed_catalogs.is_empty() {
+        return;
+    }
+
+    // Walls and floors aren't tracked per catalog, so rebuild them all from scratch
+    grid.clear_edges();
+    grid.clear_floors();
+
+    for (_, catalog) in catalogs.iter() {
+        for wall in catalog.walls.iter() {
+            grid.add_wall(wall.from, wall.to);
+        }
+
+        for door in catalog.doors.iter() {
+            grid.set_edge(TilePosition(door.tile), door.side, Some(Edge::Door));
+        }
+
+        for placement in catalog.floors.iter() {
+            for tile in placement.tiles() {
+                grid.set_floor(tile, placement.floor);
+            }
+        }
+    }
+
+    if !place_objects.0 {
+        return;
+    }
+
+    for catalog_id in changed_catalogs {
+        // Hot reload: throw away everything the previous version of the catalog placed
+        for (entity, placed_from) in placed.iter() {
+            if placed_from.0 == catalog_id {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+
+        let Some(catalog) = catalogs.get(catalog_id) else {
+            continue;
+        };
+
+        for placement in catalog.placements.iter() {
+            let Some(definition) = catalog.get(&placement.object) else {
+                println!("Unknown object in catalog placements: {}", placement.object);
+                continue;
+            };
+
+            let entity = create_object(
+                &mut commands,
+                definition.clone(),
+                placement.position,
+                Rotation::None,
+                asset_server.as_ref(),
+            );
+            commands
+                .entity(entity)
+                .insert(PlacedFromCatalog(catalog_id));
+        }
+    }
+}
+
+pub struct ObjectCatalogPlugin;
+
+impl Plugin for ObjectCatalogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ObjectCatalog>()
+            .init_resource::<PlaceCatalogObjects>()
+            .init_asset_loader::<ObjectCatalogLoader>()
+            .add_systems(Startup, load_object_catalogs)
+            .add_systems(Update, place_catalog_objects);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_catalog_parses() {
+        let catalog: ObjectCatalog =
+            ron::from_str(include_str!("../assets/objects/basic.objects.ron")).unwrap();
+
+        assert!(!catalog.objects.is_empty());
+
+        for object in catalog.objects.iter() {
+            assert!(object.capacity() > 0, "{} has no slots", object.name);
+            assert!(!object.actions.is_empty(), "{} has no actions", object.name);
+        }
+
+        for placement in catalog.placements.iter() {
+            assert!(catalog.get(&placement.object).is_some());
+        }
+    }
+
+    #[test]
+    fn basic_catalog_builds_a_house() {
+        let catalog: ObjectCatalog =
+            ron::from_str(include_str!("../assets/objects/basic.objects.ron")).unwrap();
+
+        let mut grid = WorldGrid::default();
+
+        for wall in catalog.walls.iter() {
+            grid.add_wall(wall.from, wall.to);
+        }
+
+        for door in catalog.doors.iter() {
+            grid.set_edge(TilePosition(door.tile), door.side, Some(Edge::Door));
+        }
+
+        let rooms = crate::room::detect_rooms(&grid);
+        let indoor_rooms = rooms.iter().filter(|room| !room.outdoors).count();
+
+        assert_eq!(indoor_rooms, 2);
+    }
+
+    #[test]
+    fn floors_cover_every_tile_between_the_corners() {
+        let placement = FloorPlacement {
+            from: IVec2::new(3, 2),
+            to: IVec2::new(1, 1),
+            floor: Floor::Wood,
+        };
+        let tiles: Vec<_> = placement.tiles().collect();
+
+        assert_eq!(tiles.len(), 6);
+        assert!(tiles.contains(&TilePosition::new(1, 1)));
+        assert!(tiles.contains(&TilePosition::new(3, 2)));
+    }
+}
@@ -0,0 +1,638 @@
+// Changes the player makes to the world, applied as commands that can be taken back
+
+use std::{collections::VecDeque, sync::Arc};
+
+use bevy::{ecs::system::Command, prelude::*};
+
+use crate::{
+    catalog::PlacedFromCatalog,
+    fella::{BasicMotive, BasicMotives, WalkTarget},
+    object::{object_bundle, InteractableObject},
+    utility::ObjectDefinition,
+    world::{PreviousWorldPosition, Rotation, WorldPosition},
+};
+
+// Undoing or redoing an edit can bring back a despawned object as a new entity.
+// Every other edit referring to the old entity has to be pointed at the new one.
+pub struct Respawned {
+    pub old: Entity,
+    pub new: Entity,
+}
+
+pub trait Edit: Send + Sync + 'static {
+    fn apply(&mut self, world: &mut World) -> Option<Respawned>;
+    fn undo(&mut self, world: &mut World) -> Option<Respawned>;
+    fn remap(&mut self, old: Entity, new: Entity);
+    fn describe(&self) -> String;
+}
+
+fn spawn_object(world: &mut World, object: &InteractableObject, position: Vec2) -> Entity {
+    let asset_server = world.resource::<AssetServer>().clone();
+    let bundle = object_bundle(
+        object.definition.clone(),
+        position,
+        object.rotation,
+        &asset_server,
+    );
+
+    world.spawn(bundle).id()
+}
+
+fn despawn_object(world: &mut World, object: Entity) {
+    if let Some(entity) = world.get_entity_mut(object) {
+        entity.despawn_recursive();
+    }
+}
+
+fn remap_entity(entity: &mut Entity, old: Entity, new: Entity) {
+    if *entity == old {
+        *entity = new;
+    }
+}
+
+pub struct PlaceObject {
+    object: InteractableObject,
+    position: Vec2,
+    // Set once the object has been spawned
+    entity: Option<Entity>,
+}
+
+impl PlaceObject {
+    pub fn new(definition: Arc<ObjectDefinition>, position: Vec2, rotation: Rotation) -> Self {
+        PlaceObject {
+            object: InteractableObject {
+                definition,
+                rotation,
+            },
+            position,
+            entity: None,
+        }
+    }
+}
+
+impl Edit for PlaceObject {
+    fn apply(&mut self, world: &mut World) -> Option<Respawned> {
+        let new = spawn_object(world, &self.object, self.position);
+        let old = self.entity.replace(new)?;
+
+        Some(Respawned { old, new })
+    }
+
+    fn undo(&mut self, world: &mut World) -> Option<Respawned> {
+        if let Some(entity) = self.entity {
+            despawn_object(world, entity);
+        }
+
+        None
+    }
+
+    fn remap(&mut self, old: Entity, new: Entity) {
+        if let Some(entity) = self.entity.as_mut() {
+            remap_entity(entity, old, new);
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("Place {}", self.object.definition.name)
+    }
+}
+
+pub struct RemoveObject {
+    entity: Entity,
+    // What the object was like, so it can be put back
+    removed: Option<(InteractableObject, Vec2)>,
+}
+
+impl RemoveObject {
+    pub fn new(entity: Entity) -> Self {
+        RemoveObject {
+            entity,
+            removed: None,
+        }
+    }
+}
+
+impl Edit for RemoveObject {
+    fn apply(&mut self, world: &mut World) -> Option<Respawned> {
+        let entity = world.get_entity(self.entity)?;
+        let object = entity.get::<InteractableObject>()?.clone();
+        let position = entity.get::<WorldPosition>()?.0;
+
+        self.removed = Some((object, position));
+        despawn_object(world, self.entity);
+
+        None
+    }
+
+    fn undo(&mut self, world: &mut World) -> Option<Respawned> {
+        let (object, position) = self.removed.as_ref()?;
+        let new = spawn_object(world, object, *position);
+        let old = std::mem::replace(&mut self.entity, new);
+
+        Some(Respawned { old, new })
+    }
+
+    fn remap(&mut self, old: Entity, new: Entity) {
+        remap_entity(&mut self.entity, old, new);
+    }
+
+    fn describe(&self) -> String {
+        match &self.removed {
+            Some((object, _)) => format!("Remove {}", object.definition.name),
+            None => String::from("Remove object"),
+        }
+    }
+}
+
+// Moves and/or turns an object
+pub struct MoveObject {
+    entity: Entity,
+    to: (Vec2, Rotation),
+    from: Option<(Vec2, Rotation)>,
+}
+
+impl MoveObject {
+    pub fn new(entity: Entity, position: Vec2, rotation: Rotation) -> Self {
+        MoveObject {
+            entity,
+            to: (position, rotation),
+            from: None,
+        }
+    }
+
+    // Returns where the object was before
+    fn set_placement(
+        &self,
+        world: &mut World,
+        (position, rotation): (Vec2, Rotation),
+    ) -> Option<(Vec2, Rotation)> {
+        let mut entity = world.get_entity_mut(self.entity)?;
+
+        let mut world_position = entity.get_mut::<WorldPosition>()?;
+        let previous_position = std::mem::replace(&mut world_position.0, position);
+
+        let mut object = entity.get_mut::<InteractableObject>()?;
+        let previous_rotation = std::mem::replace(&mut object.rotation, rotation);
+
+        // Reloading the catalog mustn't put the object back where it was
+        entity.remove::<PlacedFromCatalog>();
+
+        Some((previous_position, previous_rotation))
+    }
+}
+
+impl Edit for MoveObject {
+    fn apply(&mut self, world: &mut World) -> Option<Respawned> {
+        self.from = self.set_placement(world, self.to);
+        None
+    }
+
+    fn undo(&mut self, world: &mut World) -> Option<Respawned> {
+        if let Some(from) = self.from {
+            self.set_placement(world, from);
+        }
+
+        None
+    }
+
+    fn remap(&mut self, old: Entity, new: Entity) {
+        remap_entity(&mut self.entity, old, new);
+    }
+
+    fn describe(&self) -> String {
+        String::from("Move object")
+    }
+}
+
+// Picks a fella up and puts them down somewhere else
+pub struct MoveFella {
+    entity: Entity,
+    to: Vec2,
+    from: Option<Vec2>,
+}
+
+impl MoveFella {
+    pub fn new(entity: Entity, position: Vec2) -> Self {
+        MoveFella {
+            entity,
+            to: position,
+            from: None,
+        }
+    }
+
+    // Returns where the fella was before
+    fn set_position(&self, world: &mut World, position: Vec2) -> Option<Vec2> {
+        let mut entity = world.get_entity_mut(self.entity)?;
+
+        let mut world_position = entity.get_mut::<WorldPosition>()?;
+        let previous = std::mem::replace(&mut world_position.0, position);
+
+        // Teleports rather than sliding over on screen
+        if let Some(mut drawn_from) = entity.get_mut::<PreviousWorldPosition>() {
+            drawn_from.0 = position;
+        }
+
+        // Standing around stays standing around, otherwise the path is planned again from here
+        let mut walk_target = entity.get_mut::<WalkTarget>()?;
+        if walk_target.target == previous {
+            walk_target.target = position;
+        } else {
+            walk_target.set_changed();
+        }
+
+        Some(previous)
+    }
+}
+
+impl Edit for MoveFella {
+    fn apply(&mut self, world: &mut World) -> Option<Respawned> {
+        self.from = self.set_position(world, self.to);
+        None
+    }
+
+    fn undo(&mut self, world: &mut World) -> Option<Respawned> {
+        if let Some(from) = self.from {
+            self.set_position(world, from);
+        }
+
+        None
+    }
+
+    fn remap(&mut self, old: Entity, new: Entity) {
+        remap_entity(&mut self.entity, old, new);
+    }
+
+    fn describe(&self) -> String {
+        String::from("Move fella")
+    }
+}
+
+pub struct SetMotive {
+    entity: Entity,
+    motive: BasicMotive,
+    value: f32,
+    previous: Option<f32>,
+}
+
+impl SetMotive {
+    pub fn new(entity: Entity, motive: BasicMotive, value: f32) -> Self {
+        SetMotive {
+            entity,
+            motive,
+            value,
+            previous: None,
+        }
+    }
+
+    // Returns what the motive was before
+    fn set_value(&self, world: &mut World, value: f32) -> Option<f32> {
+        let mut motives = world.get_mut::<BasicMotives>(self.entity)?;
+        let previous = motives.get(self.motive);
+        motives.set(self.motive, value);
+
+        Some(previous)
+    }
+}
+
+impl Edit for SetMotive {
+    fn apply(&mut self, world: &mut World) -> Option<Respawned> {
+        self.previous = self.set_value(world, self.value);
+        None
+    }
+
+    fn undo(&mut self, world: &mut World) -> Option<Respawned> {
+        if let Some(previous) = self.previous {
+            self.set_value(world, previous);
+        }
+
+        None
+    }
+
+    fn remap(&mut self, old: Entity, new: Entity) {
+        remap_entity(&mut self.entity, old, new);
+    }
+
+    fn describe(&self) -> String {
+        format!("Set {:?} to {:.2}", self.motive, self.value)
+    }
+}
+
+// Oldest edits are forgotten past this many
+const MAX_EDIT_HISTORY: usize = 100;
+
+#[derive(Resource, Default)]
+pub struct EditHistory {
+    // Edits that have been applied, most recent last
+    done: VecDeque<Box<dyn Edit>>,
+    // Edits that have been undone, most recently undone last
+    undone: Vec<Box<dyn Edit>>,
+}
+
+impl EditHistory {
+    fn remap(&mut self, respawned: Option<Respawned>) {
+        let Some(Respawned { old, new }) = respawned else {
+            return;
+        };
+
+        for edit in self.done.iter_mut().chain(self.undone.iter_mut()) {
+            edit.remap(old, new);
+        }
+    }
+
+    fn push_done(&mut self, edit: Box<dyn Edit>) {
+        self.done.push_back(edit);
+
+        if self.done.len() > MAX_EDIT_HISTORY {
+            self.done.pop_front();
+        }
+    }
+
+    pub fn apply(&mut self, world: &mut World, mut edit: Box<dyn Edit>) {
+        let respawned = edit.apply(world);
+        self.remap(respawned);
+
+        println!("{}", edit.describe());
+        self.push_done(edit);
+
+        // A new edit starts a new branch of history
+        self.undone.clear();
+    }
+
+    pub fn undo(&mut self, world: &mut World) {
+        let Some(mut edit) = self.done.pop_back() else {
+            return;
+        };
+
+        let respawned = edit.undo(world);
+        self.remap(respawned);
+
+        println!("Undo: {}", edit.describe());
+        self.undone.push(edit);
+    }
+
+    pub fn redo(&mut self, world: &mut World) {
+        let Some(mut edit) = self.undone.pop() else {
+            return;
+        };
+
+        let respawned = edit.apply(world);
+        self.remap(respawned);
+
+        println!("Redo: {}", edit.describe());
+        self.push_done(edit);
+    }
+}
+
+// Applies an edit and records it in the history
+pub struct ApplyEdit(pub Box<dyn Edit>);
+
+impl Command for ApplyEdit {
+    fn apply(self, world: &mut World) {
+        world.resource_scope(|world, mut history: Mut<EditHistory>| {
+            history.apply(world, self.0);
+        });
+    }
+}
+
+pub struct UndoEdit;
+
+impl Command for UndoEdit {
+    fn apply(self, world: &mut World) {
+        world.resource_scope(|world, mut history: Mut<EditHistory>| {
+            history.undo(world);
+        });
+    }
+}
+
+pub struct RedoEdit;
+
+impl Command for RedoEdit {
+    fn apply(self, world: &mut World) {
+        world.resource_scope(|world, mut history: Mut<EditHistory>| {
+            history.redo(world);
+        });
+    }
+}
+
+// Ctrl+Z to undo, Ctrl+Y or Ctrl+Shift+Z to redo
+fn handle_undo_keys(mut commands: Commands, keys: Res<Input<KeyCode>>) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if !ctrl {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Y) || (shift && keys.just_pressed(KeyCode::Z)) {
+        commands.add(RedoEdit);
+    } else if keys.just_pressed(KeyCode::Z) {
+        commands.add(UndoEdit);
+    }
+}
+
+pub struct EditPlugin;
+
+impl Plugin for EditPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditHistory>()
+            .add_systems(Update, handle_undo_keys);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::UVec2;
+
+    use crate::{
+        test_utils::{definition, world_with_assets},
+        time::SimulationTime,
+    };
+
+    use super::*;
+
+    fn bed() -> Arc<ObjectDefinition> {
+        Arc::new(ObjectDefinition {
+            sprite: String::from("gfx/bed.aseprite"),
+            footprint: UVec2::new(1, 2),
+            interaction_slots: vec![Vec2::new(-1.0, 0.0)],
+            ..definition("Bed")
+        })
+    }
+
+    fn objects(world: &mut World) -> Vec<(Vec2, Rotation)> {
+        world
+            .query::<(&InteractableObject, &WorldPosition)>()
+            .iter(world)
+            .map(|(object, position)| (position.0, object.rotation))
+            .collect()
+    }
+
+    #[test]
+    fn placing_can_be_undone() {
+        let mut world = world_with_assets();
+        let mut history = EditHistory::default();
+
+        let edit = PlaceObject::new(bed(), Vec2::new(1.0, 1.0), Rotation::None);
+        history.apply(&mut world, Box::new(edit));
+        assert_eq!(objects(&mut world).len(), 1);
+
+        history.undo(&mut world);
+        assert!(objects(&mut world).is_empty());
+    }
+
+    #[test]
+    fn undoing_a_removal_keeps_older_edits_working() {
+        let mut world = world_with_assets();
+        let mut history = EditHistory::default();
+
+        history.apply(
+            &mut world,
+            Box::new(PlaceObject::new(bed(), Vec2::ZERO, Rotation::None)),
+        );
+        let bed = world
+            .query_filtered::<Entity, With<InteractableObject>>()
+            .single(&world);
+
+        history.apply(
+            &mut world,
+            Box::new(MoveObject::new(bed, Vec2::new(2.0, 0.0), Rotation::Half)),
+        );
+        history.apply(&mut world, Box::new(RemoveObject::new(bed)));
+        assert!(objects(&mut world).is_empty());
+
+        // The bed comes back as a new entity, which the move has to know about
+        history.undo(&mut world);
+        assert_eq!(
+            objects(&mut world),
+            vec![(Vec2::new(2.0, 0.0), Rotation::Half)]
+        );
+
+        history.undo(&mut world);
+        assert_eq!(objects(&mut world), vec![(Vec2::ZERO, Rotation::None)]);
+
+        history.undo(&mut world);
+        assert!(objects(&mut world).is_empty());
+    }
+
+    #[test]
+    fn moved_objects_no_longer_belong_to_their_catalog() {
+        let mut world = world_with_assets();
+        let mut history = EditHistory::default();
+
+        let bed = world
+            .spawn((
+                InteractableObject {
+                    definition: bed(),
+                    rotation: Rotation::None,
+                },
+                WorldPosition(Vec2::ZERO),
+                PlacedFromCatalog(AssetId::default()),
+            ))
+            .id();
+
+        history.apply(
+            &mut world,
+            Box::new(MoveObject::new(bed, Vec2::ONE, Rotation::None)),
+        );
+
+        assert!(world.get::<PlacedFromCatalog>(bed).is_none());
+    }
+
+    #[test]
+    fn undone_edits_can_be_redone_until_something_new_happens() {
+        let mut world = world_with_assets();
+        let mut history = EditHistory::default();
+
+        history.apply(
+            &mut world,
+            Box::new(PlaceObject::new(bed(), Vec2::ZERO, Rotation::None)),
+        );
+        history.undo(&mut world);
+        history.redo(&mut world);
+        assert_eq!(objects(&mut world), vec![(Vec2::ZERO, Rotation::None)]);
+
+        // The respawned bed is a new entity, and undoing again has to find it
+        history.undo(&mut world);
+        assert!(objects(&mut world).is_empty());
+
+        history.apply(
+            &mut world,
+            Box::new(PlaceObject::new(bed(), Vec2::ONE, Rotation::None)),
+        );
+        history.redo(&mut world);
+        assert_eq!(objects(&mut world), vec![(Vec2::ONE, Rotation::None)]);
+    }
+
+    #[test]
+    fn fella_edits_can_be_undone() {
+        let mut world = world_with_assets();
+        let mut history = EditHistory::default();
+
+        let fella = world
+            .spawn((
+                BasicMotives::default(),
+                WorldPosition(Vec2::ZERO),
+                WalkTarget {
+                    target: Vec2::ZERO,
+                    assigned_at: SimulationTime::default(),
+                },
+            ))
+            .id();
+
+        history.apply(
+            &mut world,
+            Box::new(SetMotive::new(fella, BasicMotive::Hunger, 0.1)),
+        );
+        history.apply(
+            &mut world,
+            Box::new(MoveFella::new(fella, Vec2::new(3.0, 2.0))),
+        );
+
+        assert_eq!(
+            world.get::<WorldPosition>(fella).unwrap().0,
+            Vec2::new(3.0, 2.0)
+        );
+        assert_eq!(
+            world.get::<WalkTarget>(fella).unwrap().target,
+            Vec2::new(3.0, 2.0)
+        );
+        assert_eq!(
+            world
+                .get::<BasicMotives>(fella)
+                .unwrap()
+                .get(BasicMotive::Hunger),
+            0.1
+        );
+
+        history.undo(&mut world);
+        history.undo(&mut world);
+
+        assert_eq!(world.get::<WorldPosition>(fella).unwrap().0, Vec2::ZERO);
+        assert_eq!(
+            world
+                .get::<BasicMotives>(fella)
+                .unwrap()
+                .get(BasicMotive::Hunger),
+            0.5
+        );
+    }
+
+    #[test]
+    fn history_forgets_the_oldest_edits() {
+        let mut world = world_with_assets();
+        let mut history = EditHistory::default();
+
+        for _ in 0..MAX_EDIT_HISTORY + 5 {
+            history.apply(
+                &mut world,
+                Box::new(PlaceObject::new(bed(), Vec2::ZERO, Rotation::None)),
+            );
+        }
+
+        for _ in 0..MAX_EDIT_HISTORY + 5 {
+            history.undo(&mut world);
+        }
+
+        assert_eq!(objects(&mut world).len(), 5);
+    }
+}
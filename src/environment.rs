@@ -0,0 +1,263 @@
+// How pleasant each room is, which is what the Environment motive drifts towards
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    calendar::Calendar,
+    failure::Puddle,
+    fella::{BasicMotive, BasicMotives, Fella},
+    mood::update_mood,
+    object::InteractableObject,
+    personality::Personality,
+    room::{RoomId, Rooms},
+    time::{advance_time, SimulationTick, SimulationTime},
+    world::{WorldGrid, WorldPosition},
+};
+
+// Score of an empty, averagely lit room of a comfortable size
+const NEUTRAL_ENVIRONMENT: f32 = 0.5;
+const PUDDLE_PENALTY: f32 = 0.15;
+const LIGHT_WEIGHT: f32 = 0.3;
+// Rooms smaller than this feel cramped, bigger ones feel roomy
+const COMFORTABLE_ROOM_SIZE: f32 = 9.0;
+const ROOM_SIZE_WEIGHT: f32 = 0.02;
+// Indoor rooms only get some of the daylight through the windows
+const INDOOR_DAYLIGHT: f32 = 0.6;
+// Fraction of the gap to the room's score closed every tick
+const ENVIRONMENT_DRIFT_RATE: f32 = 0.01;
+
+pub struct EnvironmentInput {
+    // Sum of the environment contributions of the objects in the room
+    pub decor: f32,
+    // Average environment contribution of the room's floor tiles
+    pub floor: f32,
+    // From 0.0 (pitch black) to 1.0 (fully lit)
+    pub light: f32,
+    pub puddles: usize,
+    pub room_size: usize,
+    pub outdoors: bool,
+}
+
+pub fn environment_score(input: &EnvironmentInput) -> f32 {
+    let dirtiness = input.puddles as f32 * PUDDLE_PENALTY;
+    let lighting = (input.light.min(1.0) - 0.5) * LIGHT_WEIGHT;
+
+    // There's always enough room outside
+    let spaciousness = if input.outdoors {
+        0.0
+    } else {
+        ((input.room_size as f32 - COMFORTABLE_ROOM_SIZE) * ROOM_SIZE_WEIGHT).clamp(-0.2, 0.1)
+    };
+
+    (NEUTRAL_ENVIRONMENT + input.decor + input.floor - dirtiness + lighting + spaciousness)
+        .clamp(0.0, 1.0)
+}
+
+// How bright the sun is, fully up from 8 to 18 and down from 21 to 5 on a 24 hour day.
+// Longer or shorter days have the same sunrise and sunset, just stretched to fit.
+pub fn daylight(time: &SimulationTime, calendar: &Calendar) -> f32 {
+    let hour = calendar.day_fraction(time) * 24.0;
+
+    if !(5.0..21.0).contains(&hour) {
+        0.0
+    } else if hour < 8.0 {
+        (hour - 5.0) / 3.0
+    } else if hour < 18.0 {
+        1.0
+    } else {
+        (21.0 - hour) / 3.0
+    }
+}
+
+#[derive(Resource, Clone, Debug, Default)]
+pub struct RoomEnvironments(HashMap<RoomId, f32>);
+
+impl RoomEnvironments {
+    pub fn get(&self, room: RoomId) -> f32 {
+        self.0.get(&room).copied().unwrap_or(NEUTRAL_ENVIRONMENT)
+    }
+}
+
+fn score_room_environments(
+    time: Res<SimulationTime>,
+    calendar: Res<Calendar>,
+    grid: Res<WorldGrid>,
+    rooms: Res<Rooms>,
+    objects: Query<(&InteractableObject, &WorldPosition)>,
+    puddles: Query<&WorldPosition, With<Puddle>>,
+    mut environments: ResMut<RoomEnvironments>,
+) {
+    let mut decor: HashMap<RoomId, f32> = HashMap::new();
+    let mut lights: HashMap<RoomId, f32> = HashMap::new();
+    let mut puddle_counts: HashMap<RoomId, usize> = HashMap::new();
+
+    for (object, world_position) in objects.iter() {
+        let Some(room) = rooms.room_at(world_position) else {
+            continue;
+        };
+
+        *decor.entry(room.id).or_default() += object.definition.environment;
+        *lights.entry(room.id).or_default() += object.definition.light;
+    }
+
+    for world_position in puddles.iter() {
+        if let Some(room) = rooms.room_at(world_position) {
+            *puddle_counts.entry(room.id).or_default() += 1;
+        }
+    }
+
+    let daylight = daylight(&time, &calendar);
+
+    environments.0 = rooms
+        .iter()
+        .map(|room| {
+            let daylight = if room.outdoors {
+                daylight
+            } else {
+                daylight * INDOOR_DAYLIGHT
+            };
+
+            let floor = room
+                .tiles
+                .iter()
+                .filter_map(|&tile| grid.get(tile))
+                .map(|tile| tile.floor.environment())
+                .sum::<f32>()
+                / room.size().max(1) as f32;
+
+            let score = environment_score(&EnvironmentInput {
+                decor: decor.get(&room.id).copied().unwrap_or(0.0),
+                floor,
+                light: daylight + lights.get(&room.id).copied().unwrap_or(0.0),
+                puddles: puddle_counts.get(&room.id).copied().unwrap_or(0),
+                room_size: room.size(),
+                outdoors: room.outdoors,
+            });
+
+            (room.id, score)
+        })
+        .collect();
+}
+
+fn drift_environment_motive(
+    rooms: Res<Rooms>,
+    environments: Res<RoomEnvironments>,
+    mut fellas: Query<(&WorldPosition, &mut BasicMotives, &Personality), With<Fella>>,
+) {
+    for (world_position, mut basic_motives, personality) in fellas.iter_mut() {
+        let target = rooms
+            .room_at(world_position)
+            .map_or(NEUTRAL_ENVIRONMENT, |room| environments.get(room.id));
+
+        // Neat fellas notice their surroundings quicker
+        let rate =
+            ENVIRONMENT_DRIFT_RATE * personality.motive_sensitivity(BasicMotive::Environment);
+
+        let current = basic_motives.get(BasicMotive::Environment);
+        basic_motives.change(BasicMotive::Environment, (target - current) * rate.min(1.0));
+    }
+}
+
+pub struct EnvironmentPlugin;
+
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RoomEnvironments>().add_systems(
+            SimulationTick,
+            (
+                score_room_environments,
+                drift_environment_motive
+                    .after(score_room_environments)
+                    .before(update_mood),
+            )
+                .after(advance_time),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world::Floor;
+
+    use super::*;
+
+    fn empty_room(room_size: usize) -> EnvironmentInput {
+        EnvironmentInput {
+            decor: 0.0,
+            floor: 0.0,
+            light: 0.5,
+            puddles: 0,
+            room_size,
+            outdoors: false,
+        }
+    }
+
+    #[test]
+    fn short_days_still_have_a_day_and_a_night() {
+        let calendar = Calendar {
+            hours_per_day: 6,
+            ..default()
+        };
+        let at = |minutes| SimulationTime::from_ticks(minutes);
+
+        assert_eq!(daylight(&at(0), &calendar), 0.0);
+        assert_eq!(daylight(&at(180), &calendar), 1.0);
+        assert!(daylight(&at(90), &calendar) > 0.0);
+    }
+
+    #[test]
+    fn empty_comfortable_room_is_neutral() {
+        let score = environment_score(&empty_room(9));
+        assert!((score - NEUTRAL_ENVIRONMENT).abs() < 1e-6);
+    }
+
+    #[test]
+    fn decor_floors_and_light_make_rooms_nicer() {
+        let plain = environment_score(&empty_room(9));
+        let decorated = environment_score(&EnvironmentInput {
+            decor: 0.2,
+            ..empty_room(9)
+        });
+        let lit = environment_score(&EnvironmentInput {
+            light: 1.0,
+            ..empty_room(9)
+        });
+        let carpeted = environment_score(&EnvironmentInput {
+            floor: Floor::Carpet.environment(),
+            ..empty_room(9)
+        });
+
+        assert!(decorated > plain);
+        assert!(lit > plain);
+        assert!(carpeted > plain);
+    }
+
+    #[test]
+    fn puddles_and_cramped_rooms_make_rooms_worse() {
+        let plain = environment_score(&empty_room(9));
+        let dirty = environment_score(&EnvironmentInput {
+            puddles: 2,
+            ..empty_room(9)
+        });
+        let cramped = environment_score(&empty_room(2));
+
+        assert!(dirty < plain);
+        assert!(cramped < plain);
+    }
+
+    #[test]
+    fn score_stays_in_range() {
+        let awful = environment_score(&EnvironmentInput {
+            decor: -2.0,
+            floor: 0.0,
+            light: 0.0,
+            puddles: 10,
+            room_size: 1,
+            outdoors: false,
+        });
+
+        assert_eq!(awful, 0.0);
+    }
+}
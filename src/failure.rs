@@ -0,0 +1,285 @@
+// What happens when a motive bottoms out
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::{advance_action_queues, ActionQueue, CancelReason},
+    fella::{BasicMotive, BasicMotives, Fella, Named, WalkTarget},
+    time::{advance_time, SimulationTick, SimulationTime},
+    world::WorldPosition,
+};
+
+// Energy regained per tick while passed out
+const PASSED_OUT_ENERGY_PER_TICK: f32 = 0.01;
+
+// Passed out fellas wake up once they have this much energy
+const WAKE_UP_ENERGY: f32 = 0.5;
+
+// How long Hunger can stay empty before starvation sets in
+const STARVATION_TICKS: u64 = 240;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MotiveFailure {
+    // Ran out of energy and fell asleep on the spot
+    PassedOut,
+    // Didn't make it to the toilet in time
+    Accident,
+    // Has been out of food for too long
+    Starvation,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct MotiveFailureEvent {
+    pub fella: Entity,
+    pub failure: MotiveFailure,
+}
+
+// Fella is asleep on the floor and can't do anything until they wake up
+#[derive(Component, Clone, Debug)]
+pub struct PassedOut;
+
+// Hunger has been empty since the given time. Starvation is severe once it has lasted long enough.
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Starving {
+    pub since: SimulationTime,
+    pub severe: bool,
+}
+
+#[derive(Component)]
+pub struct Puddle;
+
+pub fn puddle_bundle(position: Vec2) -> impl Bundle {
+    (
+        Puddle,
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.9, 0.85, 0.2, 0.8),
+                custom_size: Some(Vec2::new(48.0, 24.0)),
+                ..default()
+            },
+            ..default()
+        },
+        WorldPosition(position),
+    )
+}
+
+#[allow(clippy::type_complexity)]
+fn pass_out(
+    mut commands: Commands,
+    mut fellas: Query<
+        (
+            Entity,
+            &BasicMotives,
+            &mut ActionQueue,
+            &mut WalkTarget,
+            &WorldPosition,
+        ),
+        (With<Fella>, Without<PassedOut>),
+    >,
+    mut failure_events: EventWriter<MotiveFailureEvent>,
+) {
+    for (fella, basic_motives, mut queue, mut walk_target, world_position) in fellas.iter_mut() {
+        if basic_motives.get(BasicMotive::Energy) > 0.0 {
+            continue;
+        }
+
+        queue.cancel_all(CancelReason::Interrupted);
+        walk_target.target = world_position.0;
+
+        commands.entity(fella).insert(PassedOut);
+        failure_events.send(MotiveFailureEvent {
+            fella,
+            failure: MotiveFailure::PassedOut,
+        });
+    }
+}
+
+fn sleep_it_off(
+    mut commands: Commands,
+    mut fellas: Query<(Entity, &Named, &mut BasicMotives), With<PassedOut>>,
+) {
+    for (fella, name, mut basic_motives) in fellas.iter_mut() {
+        basic_motives.change(BasicMotive::Energy, PASSED_OUT_ENERGY_PER_TICK);
+
+        if basic_motives.get(BasicMotive::Energy) >= WAKE_UP_ENERGY {
+            println!("{} woke up", name.0);
+            commands.entity(fella).remove::<PassedOut>();
+        }
+    }
+}
+
+fn have_accidents(
+    mut commands: Commands,
+    mut fellas: Query<(Entity, &mut BasicMotives, &WorldPosition), With<Fella>>,
+    mut failure_events: EventWriter<MotiveFailureEvent>,
+) {
+    for (fella, mut basic_motives, world_position) in fellas.iter_mut() {
+        if basic_motives.get(BasicMotive::Bathroom) > 0.0 {
+            continue;
+        }
+
+        basic_motives.set(BasicMotive::Bathroom, 1.0);
+        basic_motives.set(BasicMotive::Hygiene, 0.0);
+
+        commands.spawn(puddle_bundle(world_position.0));
+
+        failure_events.send(MotiveFailureEvent {
+            fella,
+            failure: MotiveFailure::Accident,
+        });
+    }
+}
+
+fn track_starvation(
+    mut commands: Commands,
+    time: Res<SimulationTime>,
+    mut fellas: Query<(Entity, &BasicMotives, Option<&mut Starving>), With<Fella>>,
+    mut failure_events: EventWriter<MotiveFailureEvent>,
+) {
+    for (fella, basic_motives, starving) in fellas.iter_mut() {
+        let hunger_empty = basic_motives.get(BasicMotive::Hunger) <= 0.0;
+
+        match (hunger_empty, starving) {
+            (true, None) => {
+                commands.entity(fella).insert(Starving {
+                    since: time.clone(),
+                    severe: false,
+                });
+            }
+            (true, Some(mut starving)) => {
+                if starving.severe || time.time_since_ticks(&starving.since) < STARVATION_TICKS {
+                    continue;
+                }
+
+                starving.severe = true;
+                failure_events.send(MotiveFailureEvent {
+                    fella,
+                    failure: MotiveFailure::Starvation,
+                });
+            }
+            (false, Some(_)) => {
+                commands.entity(fella).remove::<Starving>();
+            }
+            (false, None) => {}
+        }
+    }
+}
+
+fn log_motive_failures(fellas: Query<&Named>, mut failure_events: EventReader<MotiveFailureEvent>) {
+    for event in failure_events.read() {
+        let name = fellas
+            .get(event.fella)
+            .map_or("Someone", |name| name.0.as_str());
+
+        match event.failure {
+            MotiveFailure::PassedOut => println!("{} passed out from exhaustion", name),
+            MotiveFailure::Accident => println!("{} had an accident", name),
+            MotiveFailure::Starvation => println!("{} is starving", name),
+        }
+    }
+}
+
+pub struct MotiveFailurePlugin;
+
+impl Plugin for MotiveFailurePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MotiveFailureEvent>().add_systems(
+            SimulationTick,
+            (
+                // Their actions are cancelled on the same tick
+                pass_out.before(advance_action_queues),
+                sleep_it_off,
+                have_accidents,
+                track_starvation,
+                log_motive_failures
+                    .after(pass_out)
+                    .after(have_accidents)
+                    .after(track_starvation),
+            )
+                .after(advance_time),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        action::{ActionCancelled, ActionPlugin},
+        object::InteractableObject,
+        pathfinding::RouteFailed,
+        test_utils::{action, definition},
+        utility::{FellaAction, ObjectDefinition},
+        world::Rotation,
+    };
+
+    use super::*;
+
+    #[test]
+    fn passing_out_empties_the_queue_straight_away() {
+        let mut app = App::new();
+        app.add_plugins((ActionPlugin, MotiveFailurePlugin))
+            .add_event::<RouteFailed>()
+            .insert_resource(SimulationTime::default());
+
+        let bed = Arc::new(ObjectDefinition {
+            actions: vec![action("Sleep")],
+            ..definition("Bed")
+        });
+        let object = app
+            .world
+            .spawn((
+                InteractableObject {
+                    definition: bed.clone(),
+                    rotation: Rotation::None,
+                },
+                WorldPosition(Vec2::new(3.0, 0.0)),
+            ))
+            .id();
+
+        let mut motives = BasicMotives::default();
+        motives.set(BasicMotive::Energy, 0.0);
+
+        let mut queue = ActionQueue::default();
+        queue.push(FellaAction::UseObject(object, bed.clone(), 0));
+
+        let fella = app
+            .world
+            .spawn((
+                Fella,
+                Named(String::from("Felix Fella")),
+                motives,
+                queue,
+                WalkTarget {
+                    target: Vec2::ZERO,
+                    assigned_at: SimulationTime::default(),
+                },
+                WorldPosition(Vec2::ZERO),
+            ))
+            .id();
+
+        app.world.run_schedule(SimulationTick);
+        assert!(app.world.get::<PassedOut>(fella).is_some());
+
+        // Actions cancelled while they're out go straight away too
+        let mut queue = app.world.get_mut::<ActionQueue>(fella).unwrap();
+        queue.push(FellaAction::UseObject(object, bed, 0));
+        queue.cancel(0, CancelReason::Cancelled);
+        app.world.run_schedule(SimulationTick);
+
+        let cancelled: Vec<CancelReason> = app
+            .world
+            .resource::<Events<ActionCancelled>>()
+            .get_reader()
+            .read(app.world.resource::<Events<ActionCancelled>>())
+            .map(|event| event.reason)
+            .collect();
+
+        assert_eq!(
+            cancelled,
+            vec![CancelReason::Interrupted, CancelReason::Cancelled]
+        );
+    }
+}
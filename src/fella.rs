@@ -1,13 +1,29 @@
-use std::ops::Add;
+use std::{collections::HashMap, ops::Add};
 
-use bevy::prelude::*;
+use bevy::{asset::AssetPath, prelude::*};
 use bevy_aseprite::AsepriteBundle;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    picking::{OnPickEvent, Pickable},
-    time::{update_simulation_time, SimulationDeltaTime, SimulationTime},
-    world::WorldPosition,
+    action::{
+        advance_action_queues, count_object_claims, ActionQueue, ActionSource, ActionState,
+        TileReservations,
+    },
+    calendar::Calendar,
+    failure::PassedOut,
+    mood::{update_mood, Mood},
+    object::InteractableObject,
+    pathfinding::{plan_paths, Path},
+    personality::Personality,
+    picking::Pickable,
+    rng::{FellaRng, SimRng},
+    time::{advance_time, SimulationTick, SimulationTime},
+    utility::{
+        pick_weighted_top_n, score_action, FellaAction, ScoreActionInput, MIN_ACTION_SCORE,
+        TOP_ACTION_COUNT,
+    },
+    world::{PreviousWorldPosition, TilePosition, WorldGrid, WorldPosition},
 };
 
 // Legally distinct from a Sim
@@ -17,7 +33,11 @@ pub struct Fella;
 #[derive(Component)]
 pub struct Named(pub String);
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+// Stays the same for as long as the fella exists, saves included, unlike their entity
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FellaId(pub u64);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub enum BasicMotive {
     Hunger = 0,
@@ -65,22 +85,11 @@ pub struct BasicMotives([MotiveValue; 8]);
 pub struct BasicMotivesDelta([f32; 8]);
 
 impl BasicMotivesDelta {
-    pub fn add(&mut self, motive: BasicMotive, value: f32) {
-        self.0[motive as usize] += value;
-    }
-
-    pub fn get(&self, motive: BasicMotive) -> f32 {
-        self.0[motive as usize]
-    }
-
-    pub fn scale(&mut self, factor: f32) {
-        for value in self.0.iter_mut() {
-            *value *= factor;
-        }
+    pub fn scale_motive(&mut self, motive: BasicMotive, factor: f32) {
+        self.0[motive as usize] *= factor;
     }
 }
 
-#[allow(dead_code)]
 impl BasicMotives {
     pub fn get(&self, motive: BasicMotive) -> f32 {
         self.0[motive as usize].0
@@ -102,117 +111,368 @@ impl BasicMotives {
     }
 }
 
-pub fn create_fella(
-    commands: &mut Commands,
+pub fn fella_bundle(
+    id: FellaId,
     name: impl Into<String>,
-    sprite_path: &'static str,
+    personality: Personality,
+    sprite_path: impl Into<AssetPath<'static>>,
     position: Vec2,
+    sim_rng: &SimRng,
     asset_server: &AssetServer,
-) {
-    commands.spawn((
-        Fella,
+) -> impl Bundle {
+    (
+        (Fella, id, FellaRng::new(sim_rng, id)),
         BasicMotives::default(),
+        Mood::default(),
         Named(name.into()),
+        personality,
         AsepriteBundle {
             aseprite: asset_server.load(sprite_path),
             transform: Transform::from_scale(Vec3::splat(2.0)),
             ..default()
         },
         WorldPosition(position),
+        PreviousWorldPosition(position),
         WalkTarget {
             target: position,
             assigned_at: SimulationTime::default(),
         },
+        Path::default(),
+        ActionQueue::default(),
         Pickable,
         Visibility::default(),
         InheritedVisibility::default(),
         ViewVisibility::default(),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_fella(
+    commands: &mut Commands,
+    id: FellaId,
+    name: impl Into<String>,
+    personality: Personality,
+    sprite_path: &'static str,
+    position: Vec2,
+    sim_rng: &SimRng,
+    asset_server: &AssetServer,
+) {
+    commands.spawn(fella_bundle(
+        id,
+        name,
+        personality,
+        sprite_path,
+        position,
+        sim_rng,
+        asset_server,
     ));
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct WalkTarget {
-    target: Vec2,
-    assigned_at: SimulationTime,
+    pub target: Vec2,
+    pub assigned_at: SimulationTime,
 }
 
-fn assign_random_walk_target(
+// A new action has to score this many times more than the current one to interrupt it
+const INTERRUPT_SCORE_RATIO: f32 = 3.0;
+
+// Scores every available action of every object that has room for one more fella
+fn score_advertised_actions(
+    objects: &Query<(Entity, &InteractableObject, &WorldPosition)>,
+    claims: &HashMap<Entity, usize>,
+    time: &SimulationTime,
+    calendar: &Calendar,
+    world_position: &WorldPosition,
+    motives: &BasicMotives,
+    personality: &Personality,
+) -> Vec<(FellaAction, f32)> {
+    objects
+        .iter()
+        .filter(|(entity, object, _)| {
+            claims.get(entity).copied().unwrap_or(0) < object.definition.capacity()
+        })
+        .flat_map(|(entity, object, object_position)| {
+            let distance = world_position.0.distance(object_position.0);
+
+            // Every action of the object is advertised and scored separately
+            object
+                .definition
+                .actions
+                .iter()
+                .enumerate()
+                .filter(|(_, action)| action.is_available(motives, time, calendar))
+                .map(move |(index, action)| {
+                    let score = score_action(&ScoreActionInput {
+                        current_motives: motives,
+                        personality,
+                        action,
+                        distance,
+                    });
+
+                    let action = FellaAction::UseObject(entity, object.definition.clone(), index);
+                    (action, score)
+                })
+        })
+        .collect()
+}
+
+#[allow(clippy::type_complexity)]
+fn choose_next_action(
     time: Res<SimulationTime>,
-    mut query: Query<(&mut WalkTarget, &WorldPosition)>,
+    calendar: Res<Calendar>,
+    grid: Res<WorldGrid>,
+    reservations: Res<TileReservations>,
+    objects: Query<(Entity, &InteractableObject, &WorldPosition)>,
+    mut fellas: Query<
+        (
+            &Named,
+            &mut ActionQueue,
+            &mut WalkTarget,
+            &WorldPosition,
+            &BasicMotives,
+            &Personality,
+            &mut FellaRng,
+        ),
+        (With<Fella>, Without<PassedOut>),
+    >,
 ) {
-    let mut rng = rand::thread_rng();
-
-    for (mut walk_target, world_position) in query.iter_mut() {
-        // If the target is reached, assign a new one
-        if walk_target.target.distance(world_position.0) < 0.1 {
-            // Wait a few ticks before assigning a new target to avoid jitter
-            if time.time_since_ticks(&walk_target.assigned_at) < 2 {
+    let mut claims = count_object_claims(fellas.iter().map(|(_, queue, ..)| queue));
+
+    for (name, mut queue, mut walk_target, world_position, motives, personality, mut rng) in
+        fellas.iter_mut()
+    {
+        if let Some(current) = queue.current() {
+            // The player knows best
+            if current.source == ActionSource::Player {
                 continue;
-            };
+            }
+
+            // Busy, but something much more pressing might have come up
+            let current_score = score_action(&ScoreActionInput {
+                current_motives: motives,
+                personality,
+                action: current.action.object_action(),
+                distance: 0.0,
+            });
+
+            let best = score_advertised_actions(
+                &objects,
+                &claims,
+                &time,
+                &calendar,
+                world_position,
+                motives,
+                personality,
+            )
+            .into_iter()
+            .filter(|(action, _)| *action != current.action)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
 
-            let x: f32 = rng.gen_range(-8.0..8.0);
-            let y: f32 = rng.gen_range(-4.0..4.0);
-            assert!(x.is_finite() && y.is_finite());
+            if let Some((action, score)) = best {
+                if score > MIN_ACTION_SCORE && score > current_score * INTERRUPT_SCORE_RATIO {
+                    println!("{} has something more urgent to do: {}", name.0, action);
 
-            walk_target.target = Vec2::new(x, y);
-            walk_target.assigned_at = time.clone();
+                    *claims.entry(action.object()).or_insert(0) += 1;
+                    queue.interrupt(action);
+                }
+            }
 
-            println!("Assigned new walk target: {:?}", walk_target);
+            continue;
         }
+
+        // Only idle fellas (ones that have reached their target) pick something new to do
+        if walk_target.target.distance(world_position.0) >= 0.1 {
+            continue;
+        }
+
+        // Wait a few ticks before assigning a new target to avoid jitter
+        if time.time_since_ticks(&walk_target.assigned_at) < 2 {
+            continue;
+        };
+
+        let candidates = score_advertised_actions(
+            &objects,
+            &claims,
+            &time,
+            &calendar,
+            world_position,
+            motives,
+            personality,
+        );
+        let chosen =
+            pick_weighted_top_n(candidates, TOP_ACTION_COUNT, MIN_ACTION_SCORE, &mut rng.0);
+
+        match chosen {
+            Some(action) => {
+                println!("{} decided to {}", name.0, action);
+
+                *claims.entry(action.object()).or_insert(0) += 1;
+                queue.push(action);
+            }
+            // Nothing worth doing, so just wander around
+            None => {
+                if let Some(target) = random_walk_target(&grid, &reservations, &mut rng.0) {
+                    walk_target.target = target;
+                }
+
+                walk_target.assigned_at = time.clone();
+
+                println!("Assigned new walk target: {:?}", walk_target);
+            }
+        };
     }
 }
 
-fn move_to_walk_target(
-    delta: Res<SimulationDeltaTime>,
-    mut query: Query<(&mut WorldPosition, &WalkTarget)>,
-) {
-    let Some(delta) = delta.0 else {
-        return;
-    };
+// Somewhere a fella could stand around without getting in anyone's way
+fn random_walk_target(
+    grid: &WorldGrid,
+    reservations: &TileReservations,
+    rng: &mut impl Rng,
+) -> Option<Vec2> {
+    (0..10).find_map(|_| {
+        let x: f32 = rng.gen_range(-8.0..8.0);
+        let y: f32 = rng.gen_range(-4.0..4.0);
+        assert!(x.is_finite() && y.is_finite());
+
+        let target = Vec2::new(x, y);
+        let tile = TilePosition::from_world(&WorldPosition(target));
+
+        (grid.is_walkable(tile) && !reservations.0.contains_key(&tile)).then_some(target)
+    })
+}
 
-    for (mut world_position, walk_target) in query.iter_mut() {
-        let direction = (walk_target.target - world_position.0).normalize();
+// In tiles per tick
+const WALK_SPEED: f32 = 1.0;
 
-        // If direction is NaN, just skip this
-        if !direction.is_finite() {
-            continue;
-        }
+fn move_to_walk_target(mut query: Query<(&mut WorldPosition, &mut Path), Without<PassedOut>>) {
+    for (mut world_position, mut path) in query.iter_mut() {
+        let mut distance_left = WALK_SPEED;
+
+        // Fast fellas can get past several waypoints in one tick
+        while let Some(&waypoint) = path.waypoints.front() {
+            let distance = world_position.0.distance(waypoint);
 
-        world_position.0 += direction * 1.0 * delta as f32;
+            if distance > distance_left {
+                let direction = (waypoint - world_position.0) / distance;
+                world_position.0 += direction * distance_left;
+                break;
+            }
+
+            world_position.0 = waypoint;
+            distance_left -= distance;
+            path.waypoints.pop_front();
+        }
     }
 }
 
-#[derive(Resource)]
-pub struct SelectedFella(pub Option<Entity>);
+// How close fellas can get to each other
+const FELLA_RADIUS: f32 = 0.3;
+
+// How easily a fella gets pushed out of the way by others
+fn give_way_factor(path: &Path, queue: &ActionQueue, is_passed_out: bool) -> f32 {
+    let is_performing = matches!(
+        queue.current().map(|current| &current.state),
+        Some(ActionState::Performing { .. })
+    );
+
+    // Fellas that are busy or out cold hold their ground,
+    // and ones standing around make way for ones walking somewhere
+    if is_passed_out || is_performing {
+        0.0
+    } else if path.waypoints.is_empty() {
+        1.0
+    } else {
+        0.25
+    }
+}
 
-fn select_fella(
-    mut selected_fella: ResMut<SelectedFella>,
-    mut select_fella_events: EventReader<OnPickEvent>,
-    mut fellas: Query<(Entity, &mut TextureAtlasSprite), With<Fella>>,
+// Pushes overlapping fellas apart, and a bit to the side so that
+// fellas walking towards each other can get past
+#[allow(clippy::type_complexity)]
+fn keep_fellas_apart(
+    grid: Res<WorldGrid>,
+    mut fellas: Query<
+        (
+            Entity,
+            &mut WorldPosition,
+            &mut WalkTarget,
+            &Path,
+            &ActionQueue,
+            Has<PassedOut>,
+        ),
+        With<Fella>,
+    >,
 ) {
-    for event in select_fella_events.read() {
-        selected_fella.0 = Some(event.0);
-        println!("Selected fella: {:?}", event.0);
-
-        for (fella_entity, mut sprite) in fellas.iter_mut() {
-            sprite.color = if fella_entity == event.0 {
-                Color::RED
-            } else {
-                Color::WHITE
-            };
+    let fella_states: Vec<(Entity, Vec2, f32)> = fellas
+        .iter()
+        .map(|(fella, world_position, _, path, queue, is_passed_out)| {
+            let give_way = give_way_factor(path, queue, is_passed_out);
+            (fella, world_position.0, give_way)
+        })
+        .collect();
+
+    let mut pushes: HashMap<Entity, Vec2> = HashMap::new();
+
+    for (index, &(a, a_position, a_give_way)) in fella_states.iter().enumerate() {
+        for &(b, b_position, b_give_way) in fella_states.iter().skip(index + 1) {
+            let offset = a_position - b_position;
+            let overlap = FELLA_RADIUS * 2.0 - offset.length();
+            let total_give_way = a_give_way + b_give_way;
+
+            if overlap <= 0.0 || total_give_way == 0.0 {
+                continue;
+            }
+
+            // Fellas on the exact same spot still need to go somewhere
+            let away = offset.try_normalize().unwrap_or(Vec2::X);
+            let direction = (away + away.perp() * 0.5).normalize();
+
+            *pushes.entry(a).or_default() += direction * overlap * a_give_way / total_give_way;
+            *pushes.entry(b).or_default() -= direction * overlap * b_give_way / total_give_way;
+        }
+    }
+
+    for (fella, push) in pushes {
+        let Ok((_, mut world_position, mut walk_target, path, queue, _)) = fellas.get_mut(fella)
+        else {
+            continue;
+        };
+
+        let pushed_to = WorldPosition(world_position.0 + push);
+        let tile = TilePosition::from_world(&pushed_to);
+
+        // Don't get pushed into walls or furniture
+        if tile != TilePosition::from_world(&world_position) && !grid.is_walkable(tile) {
+            continue;
+        }
+
+        *world_position = pushed_to;
+
+        if !path.waypoints.is_empty() {
+            continue;
+        }
+
+        let is_walking_to_slot = matches!(
+            queue.current().map(|current| &current.state),
+            Some(ActionState::WalkingTo { .. })
+        );
+
+        // Fellas waiting at an object walk back to their spot,
+        // everyone else is happy to stand wherever they ended up
+        if is_walking_to_slot {
+            walk_target.set_changed();
+        } else {
+            walk_target.target = world_position.0;
         }
     }
 }
 
-fn apply_need_decay(
-    delta: Res<SimulationDeltaTime>,
-    mut query: Query<&mut BasicMotives, With<Fella>>,
-) {
-    let Some(delta) = delta.0 else {
-        return;
-    };
+#[derive(Resource)]
+pub struct SelectedFella(pub Option<Entity>);
 
-    let mut default_decays = BasicMotivesDelta([
+fn apply_need_decay(mut query: Query<(&mut BasicMotives, &Personality), With<Fella>>) {
+    let default_decays = BasicMotivesDelta([
         -0.006, // Hunger
         -0.005, // Bathroom
         -0.004, // Energy
@@ -220,32 +480,34 @@ fn apply_need_decay(
         -0.005, // Social
         -0.008, // Fun
         -0.005, // Comfort
-        -0.009, // Environment
+        0.0,    // Environment drifts towards the room's score instead
     ]);
 
-    default_decays.scale(delta as f32);
+    for (mut basic_motives, personality) in query.iter_mut() {
+        let mut decays = default_decays.clone();
 
-    for mut basic_motives in query.iter_mut() {
-        basic_motives.add(&default_decays);
+        for motive in ALL_MOTIVES {
+            decays.scale_motive(motive, personality.motive_sensitivity(motive));
+        }
+
+        basic_motives.add(&decays);
     }
 }
 
-#[derive(Component)]
-pub struct Waiting(pub u64);
-
 pub struct FellaPlugin;
 
 impl Plugin for FellaPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(SelectedFella(None)).add_systems(
-            Update,
+        app.add_systems(
+            SimulationTick,
             (
-                assign_random_walk_target,
-                move_to_walk_target,
-                select_fella,
+                choose_next_action.before(advance_action_queues),
+                move_to_walk_target.after(plan_paths),
+                keep_fellas_apart.after(move_to_walk_target),
                 apply_need_decay,
+                update_mood.after(apply_need_decay),
             )
-                .after(update_simulation_time),
+                .after(advance_time),
         );
     }
 }
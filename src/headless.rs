@@ -0,0 +1,481 @@
+// Running the simulation without a window for a number of in-game days, e.g. on CI,
+// and writing down how the fellas got on
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_aseprite::Aseprite;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    action::{ActionQueue, ActionState},
+    calendar::Calendar,
+    fella::{BasicMotives, Fella, Named, ALL_MOTIVES},
+    object::InteractableObject,
+    rng::{random_seed, SimRng},
+    simulation::SimulationPlugin,
+    time::{advance_time, SimulationTick, SimulationTime, TimeScale},
+};
+
+// Real time that passes every update, which at normal speed is exactly one tick
+const HEADLESS_STEP: Duration = Duration::from_secs(1);
+
+// How long to wait for the object catalogs before giving up on them
+const LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+const DEFAULT_DAYS: u64 = 7;
+const DEFAULT_OUTPUT: &str = "summary.json";
+
+#[derive(Debug, Error)]
+pub enum HeadlessError {
+    #[error("Bad argument: {0}")]
+    BadArgument(String),
+    #[error("Could not write summary: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not serialize summary: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadlessOptions {
+    pub days: u64,
+    pub output: PathBuf,
+    pub seed: Option<u64>,
+}
+
+impl HeadlessOptions {
+    // Understands --days N, --output PATH and --seed N, ignoring --headless itself
+    pub fn from_args(args: &[String]) -> Result<Self, HeadlessError> {
+        let mut options = HeadlessOptions {
+            days: DEFAULT_DAYS,
+            output: PathBuf::from(DEFAULT_OUTPUT),
+            seed: None,
+        };
+
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| HeadlessError::BadArgument(format!("{} needs a value", arg)))
+            };
+
+            match arg.as_str() {
+                "--headless" => {}
+                "--days" => {
+                    let days = value()?;
+                    options.days = days
+                        .parse()
+                        .map_err(|_| HeadlessError::BadArgument(format!("--days {}", days)))?;
+                }
+                "--output" => options.output = PathBuf::from(value()?),
+                "--seed" => {
+                    let seed = value()?;
+                    options.seed = Some(
+                        seed.parse()
+                            .map_err(|_| HeadlessError::BadArgument(format!("--seed {}", seed)))?,
+                    );
+                }
+                other => return Err(HeadlessError::BadArgument(other.to_string())),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+#[derive(Default)]
+struct FellaRecord {
+    // Sum of each motive weighted by how long it had that value, in ticks
+    motive_totals: HashMap<String, f64>,
+    ticks: f64,
+    ticks_per_action: HashMap<String, f64>,
+}
+
+#[derive(Resource, Default)]
+struct SummaryRecorder {
+    fellas: HashMap<Entity, FellaRecord>,
+}
+
+fn record_summary(
+    fellas: Query<(Entity, &BasicMotives, &ActionQueue), With<Fella>>,
+    mut recorder: ResMut<SummaryRecorder>,
+) {
+    for (fella, basic_motives, queue) in fellas.iter() {
+        let record = recorder.fellas.entry(fella).or_default();
+        record.ticks += 1.0;
+
+        for motive in ALL_MOTIVES {
+            *record
+                .motive_totals
+                .entry(format!("{:?}", motive))
+                .or_default() += basic_motives.get(motive) as f64;
+        }
+
+        let Some(current) = queue.current() else {
+            continue;
+        };
+
+        if let ActionState::Performing { .. } = current.state {
+            let name = format!(
+                "{}: {}",
+                current.action.definition().name,
+                current.action.object_action().name
+            );
+            *record.ticks_per_action.entry(name).or_default() += 1.0;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FellaSummary {
+    pub name: String,
+    pub motive_averages: BTreeMap<String, f64>,
+    // One tick is an in-game minute
+    pub minutes_per_action: BTreeMap<String, f64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SimulationSummary {
+    // Running again with this seed gives the same summary
+    pub seed: u64,
+    pub ticks: u64,
+    pub fellas: Vec<FellaSummary>,
+}
+
+fn summarize(world: &mut World) -> SimulationSummary {
+    let mut fellas: Vec<(Entity, String)> = world
+        .query_filtered::<(Entity, &Named), With<Fella>>()
+        .iter(world)
+        .map(|(fella, name)| (fella, name.0.clone()))
+        .collect();
+    fellas.sort();
+
+    let recorder = world.resource::<SummaryRecorder>();
+
+    let fellas = fellas
+        .into_iter()
+        .map(|(fella, name)| {
+            let Some(record) = recorder
+                .fellas
+                .get(&fella)
+                .filter(|record| record.ticks > 0.0)
+            else {
+                return FellaSummary {
+                    name,
+                    motive_averages: BTreeMap::new(),
+                    minutes_per_action: BTreeMap::new(),
+                };
+            };
+
+            FellaSummary {
+                name,
+                motive_averages: record
+                    .motive_totals
+                    .iter()
+                    .map(|(motive, total)| (motive.clone(), total / record.ticks))
+                    .collect(),
+                minutes_per_action: record
+                    .ticks_per_action
+                    .iter()
+                    .map(|(action, ticks)| (action.clone(), *ticks))
+                    .collect(),
+            }
+        })
+        .collect();
+
+    SimulationSummary {
+        seed: world.resource::<SimRng>().seed(),
+        ticks: world.resource::<SimulationTime>().ticks(),
+        fellas,
+    }
+}
+
+fn headless_app(seed: u64) -> App {
+    let mut app = unloaded_headless_app(seed);
+    wait_for_catalogs(&mut app);
+    app
+}
+
+// Everything but the object catalogs, which start loading on the first update
+fn unloaded_headless_app(seed: u64) -> App {
+    let mut app = App::new();
+
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        SimulationPlugin { seed },
+    ))
+    // Fellas and objects still have sprites, they just never get drawn
+    .init_asset::<Aseprite>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(HEADLESS_STEP))
+    .init_resource::<SummaryRecorder>()
+    .add_systems(SimulationTick, record_summary.after(advance_time));
+
+    app.finish();
+    app.cleanup();
+
+    app
+}
+
+// Catalogs load in the background, which takes a different number of updates every time.
+// The clock only starts once the objects are in place, so that runs can be repeated exactly.
+fn wait_for_catalogs(app: &mut App) {
+    let time_scale = std::mem::replace(
+        app.world.resource_mut::<TimeScale>().as_mut(),
+        TimeScale::Paused,
+    );
+    let started = Instant::now();
+
+    loop {
+        app.update();
+
+        let has_objects = app
+            .world
+            .query::<&InteractableObject>()
+            .iter(&app.world)
+            .next()
+            .is_some();
+
+        if has_objects {
+            // Walls, rooms and occupied tiles only catch up with the objects an update later
+            app.update();
+            break;
+        }
+
+        if started.elapsed() > LOAD_TIMEOUT {
+            println!("No objects loaded, running without them");
+            break;
+        }
+
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    *app.world.resource_mut::<TimeScale>() = time_scale;
+}
+
+fn run_for(app: &mut App, ticks: u64) {
+    let end = app.world.resource::<SimulationTime>().ticks() + ticks;
+
+    while app.world.resource::<SimulationTime>().ticks() < end {
+        app.update();
+    }
+}
+
+pub fn run_headless(options: &HeadlessOptions) -> Result<(), HeadlessError> {
+    let mut app = headless_app(options.seed.unwrap_or_else(random_seed));
+    let ticks_per_day = app.world.resource::<Calendar>().ticks_per_day();
+
+    for day in 0..options.days {
+        run_for(&mut app, ticks_per_day);
+        println!("Simulated day {} of {}", day + 1, options.days);
+    }
+
+    let summary = summarize(&mut app.world);
+    std::fs::write(&options.output, serde_json::to_string_pretty(&summary)?)?;
+    println!("Wrote summary to {}", options.output.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        catalog::ObjectCatalog, mood::critical_motives, time::SkipUntil, world::WorldPosition,
+    };
+
+    use super::*;
+
+    // Loading the catalog from disk takes however long it takes, so tests hand it over directly
+    fn loaded_headless_app(seed: u64) -> App {
+        let mut app = unloaded_headless_app(seed);
+
+        let catalog: ObjectCatalog =
+            ron::from_str(include_str!("../assets/objects/basic.objects.ron")).unwrap();
+        // A fixed id rather than a strong handle, which would free the catalog once dropped
+        let handle = Handle::weak_from_u128(0x5eed_ca7a_1060);
+        app.world
+            .resource_mut::<Assets<ObjectCatalog>>()
+            .insert(handle.clone(), catalog);
+        app.world
+            .send_event(AssetEvent::LoadedWithDependencies { id: handle.id() });
+
+        wait_for_catalogs(&mut app);
+
+        let has_objects = app
+            .world
+            .query::<&InteractableObject>()
+            .iter(&app.world)
+            .next()
+            .is_some();
+        assert!(has_objects);
+
+        app
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn options_have_defaults() {
+        let options = HeadlessOptions::from_args(&args(&["--headless"])).unwrap();
+
+        assert_eq!(options.days, DEFAULT_DAYS);
+        assert_eq!(options.output, PathBuf::from(DEFAULT_OUTPUT));
+        assert_eq!(options.seed, None);
+    }
+
+    #[test]
+    fn options_are_parsed() {
+        let options = HeadlessOptions::from_args(&args(&[
+            "--headless",
+            "--days",
+            "3",
+            "--output",
+            "out.json",
+        ]))
+        .unwrap();
+
+        assert_eq!(options.days, 3);
+        assert_eq!(options.output, PathBuf::from("out.json"));
+
+        assert!(HeadlessOptions::from_args(&args(&["--days", "lots"])).is_err());
+        assert!(HeadlessOptions::from_args(&args(&["--days"])).is_err());
+    }
+
+    #[test]
+    fn headless_simulation_summarizes_every_fella() {
+        let mut app = loaded_headless_app(0);
+        run_for(&mut app, 60);
+
+        let summary = summarize(&mut app.world);
+
+        assert_eq!(summary.fellas.len(), 2);
+
+        for fella in summary.fellas.iter() {
+            assert_eq!(fella.motive_averages.len(), ALL_MOTIVES.len());
+            assert!(fella
+                .motive_averages
+                .values()
+                .all(|average| (0.0..=1.0).contains(average)));
+        }
+    }
+
+    // Where every fella is after each update
+    fn position_trace(seed: u64, updates: usize) -> Vec<Vec<(String, Vec2)>> {
+        let mut app = loaded_headless_app(seed);
+
+        (0..updates)
+            .map(|_| {
+                app.update();
+
+                let mut positions: Vec<(String, Vec2)> = app
+                    .world
+                    .query::<(&Named, &WorldPosition)>()
+                    .iter(&app.world)
+                    .map(|(name, world_position)| (name.0.clone(), world_position.0))
+                    .collect();
+                positions.sort_by(|(a, _), (b, _)| a.cmp(b));
+                positions
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_run() {
+        // Long enough for the fellas to wander off and use some objects
+        let updates = 120;
+
+        let first = position_trace(1234, updates);
+        let second = position_trace(1234, updates);
+
+        assert_eq!(first, second);
+
+        let fellas_moved = first.first() != first.last();
+        assert!(fellas_moved);
+
+        // Otherwise the seed isn't actually doing anything
+        assert_ne!(first, position_trace(4321, updates));
+    }
+
+    // Where every fella is and how they feel after running for some ticks
+    fn state_after(app: &mut App, ticks: u64) -> Vec<(String, Vec2, Vec<f32>)> {
+        run_for(app, ticks);
+
+        let mut state: Vec<(String, Vec2, Vec<f32>)> = app
+            .world
+            .query::<(&Named, &WorldPosition, &BasicMotives)>()
+            .iter(&app.world)
+            .map(|(name, world_position, basic_motives)| {
+                let motives = ALL_MOTIVES
+                    .iter()
+                    .map(|&motive| basic_motives.get(motive))
+                    .collect();
+                (name.0.clone(), world_position.0, motives)
+            })
+            .collect();
+        state.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+        state
+    }
+
+    #[test]
+    fn every_speed_gives_the_same_run() {
+        let ticks = 120;
+
+        // A tick every four updates
+        let mut slow = loaded_headless_app(1234);
+        slow.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            250,
+        )));
+
+        // Four ticks every update
+        let mut fast = loaded_headless_app(1234);
+        *fast.world.resource_mut::<TimeScale>() = TimeScale::Fastest;
+
+        assert_eq!(state_after(&mut slow, ticks), state_after(&mut fast, ticks));
+    }
+
+    // Runs until skipping is over, which puts the clock back to normal speed
+    fn skip(app: &mut App, until: SkipUntil) {
+        *app.world.resource_mut::<TimeScale>() = TimeScale::Skip(until);
+
+        for _ in 0..10_000 {
+            app.update();
+
+            if *app.world.resource::<TimeScale>() == TimeScale::Normal {
+                return;
+            }
+        }
+
+        panic!("Skipping never stopped");
+    }
+
+    #[test]
+    fn skipping_to_morning_stops_at_morning() {
+        let mut app = loaded_headless_app(1234);
+        let calendar = app.world.resource::<Calendar>().clone();
+        let day = app.world.resource::<SimulationTime>().days(&calendar);
+
+        skip(&mut app, SkipUntil::Morning);
+
+        let time = app.world.resource::<SimulationTime>();
+        assert_eq!(time.get_hour_minute(&calendar), (7, 0));
+        assert_eq!(time.days(&calendar), day + 1);
+    }
+
+    #[test]
+    fn skipping_until_needed_stops_when_a_motive_gets_critical() {
+        let mut app = loaded_headless_app(1234);
+        let critical_before = critical_motives(&mut app.world);
+
+        skip(&mut app, SkipUntil::NeedsAttention);
+
+        assert!(!critical_motives(&mut app.world).is_subset(&critical_before));
+    }
+}
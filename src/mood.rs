@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    failure::Starving,
+    fella::{BasicMotive, BasicMotives, Fella, ALL_MOTIVES},
+};
+
+// Overall happiness of a fella, from -1.0 (miserable) to 1.0 (ecstatic)
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mood(pub f32);
+
+// How much each motive counts towards mood on average
+fn mood_weight(motive: BasicMotive) -> f32 {
+    match motive {
+        BasicMotive::Hunger | BasicMotive::Energy => 1.5,
+        BasicMotive::Bathroom | BasicMotive::Hygiene => 1.2,
+        BasicMotive::Social | BasicMotive::Fun | BasicMotive::Comfort => 1.0,
+        BasicMotive::Environment => 0.6,
+    }
+}
+
+// Motives below this are critical
+const CRITICAL_MOTIVE_LEVEL: f32 = 0.2;
+
+// How hard the single worst critical motive drags the mood down on top of the average
+const CRITICAL_MOTIVE_PENALTY: f32 = 1.2;
+
+// Long starvation is miserable no matter what else is going on
+const STARVATION_MOOD_PENALTY: f32 = 1.0;
+
+pub fn compute_mood(motives: &BasicMotives) -> f32 {
+    let mut weighted_sum = 0.0;
+    let mut total_weight = 0.0;
+    let mut worst_criticality: f32 = 0.0;
+
+    for motive in ALL_MOTIVES {
+        let value = motives.get(motive);
+        let weight = mood_weight(motive);
+
+        weighted_sum += (value * 2.0 - 1.0) * weight;
+        total_weight += weight;
+
+        let criticality = (CRITICAL_MOTIVE_LEVEL - value) / CRITICAL_MOTIVE_LEVEL;
+        worst_criticality = worst_criticality.max(criticality);
+    }
+
+    let average = weighted_sum / total_weight;
+    (average - worst_criticality * CRITICAL_MOTIVE_PENALTY).clamp(-1.0, 1.0)
+}
+
+// Every motive of every fella that's critical right now
+pub fn critical_motives(world: &mut World) -> HashSet<(Entity, BasicMotive)> {
+    world
+        .query_filtered::<(Entity, &BasicMotives), With<Fella>>()
+        .iter(world)
+        .flat_map(|(fella, basic_motives)| {
+            ALL_MOTIVES
+                .into_iter()
+                .filter(|&motive| basic_motives.get(motive) < CRITICAL_MOTIVE_LEVEL)
+                .map(move |motive| (fella, motive))
+        })
+        .collect()
+}
+
+pub fn update_mood(mut query: Query<(&BasicMotives, &mut Mood, Option<&Starving>), With<Fella>>) {
+    for (basic_motives, mut mood, starving) in query.iter_mut() {
+        let mut new_mood = compute_mood(basic_motives);
+
+        if starving.is_some_and(|starving| starving.severe) {
+            new_mood = (new_mood - STARVATION_MOOD_PENALTY).max(-1.0);
+        }
+
+        let new_mood = Mood(new_mood);
+
+        if *mood != new_mood {
+            *mood = new_mood;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn motives_at(value: f32) -> BasicMotives {
+        let mut motives = BasicMotives::default();
+
+        for motive in ALL_MOTIVES {
+            motives.set(motive, value);
+        }
+
+        motives
+    }
+
+    #[test]
+    fn full_motives_are_ecstatic() {
+        assert_eq!(compute_mood(&motives_at(1.0)), 1.0);
+    }
+
+    #[test]
+    fn empty_motives_are_miserable() {
+        assert_eq!(compute_mood(&motives_at(0.0)), -1.0);
+    }
+
+    #[test]
+    fn one_critical_motive_is_worse_than_all_mediocre() {
+        let mut starving = motives_at(0.8);
+        starving.set(BasicMotive::Hunger, 0.05);
+
+        assert!(compute_mood(&starving) < compute_mood(&motives_at(0.4)));
+    }
+}
@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy_aseprite::AsepriteBundle;
+use thiserror::Error;
+
+use crate::{
+    picking::Pickable,
+    utility::ObjectDefinition,
+    world::{Rotation, TilePosition, WorldGrid, WorldPosition},
+};
+
+// Something in the world that advertises actions to fellas
+#[derive(Component, Clone, Debug)]
+pub struct InteractableObject {
+    pub definition: Arc<ObjectDefinition>,
+    pub rotation: Rotation,
+}
+
+impl InteractableObject {
+    pub fn footprint_tiles(&self, position: &WorldPosition) -> Vec<TilePosition> {
+        WorldGrid::footprint_tiles(
+            TilePosition::from_world(position),
+            self.definition.footprint,
+            self.rotation,
+        )
+    }
+
+    // Where a fella stands to use the slot of the object at the given position
+    pub fn slot_position(&self, object_position: Vec2, slot: usize) -> Vec2 {
+        object_position
+            + self
+                .rotation
+                .rotate_offset(self.definition.interaction_slots[slot])
+    }
+
+    pub fn slot_tiles(&self, object_position: Vec2) -> Vec<TilePosition> {
+        (0..self.definition.capacity())
+            .map(|slot| self.slot_position(object_position, slot))
+            .map(|slot_position| TilePosition::from_world(&WorldPosition(slot_position)))
+            .collect()
+    }
+}
+
+pub fn object_bundle(
+    definition: Arc<ObjectDefinition>,
+    position: Vec2,
+    rotation: Rotation,
+    asset_server: &AssetServer,
+) -> impl Bundle {
+    (
+        AsepriteBundle {
+            aseprite: asset_server.load(definition.sprite.clone()),
+            transform: Transform::from_scale(Vec3::splat(2.0))
+                .with_rotation(Quat::from_rotation_z(rotation.radians())),
+            ..default()
+        },
+        InteractableObject {
+            definition,
+            rotation,
+        },
+        WorldPosition(position),
+        Pickable,
+    )
+}
+
+pub fn create_object(
+    commands: &mut Commands,
+    definition: Arc<ObjectDefinition>,
+    position: Vec2,
+    rotation: Rotation,
+    asset_server: &AssetServer,
+) -> Entity {
+    commands
+        .spawn(object_bundle(definition, position, rotation, asset_server))
+        .id()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum PlacementError {
+    #[error("Not enough room")]
+    Occupied,
+    #[error("Nowhere to stand to use it")]
+    SlotBlocked,
+    #[error("Would block something else")]
+    BlocksSlot,
+}
+
+// Checks whether an object can go at the given position. The object being moved,
+// if any, doesn't get in its own way.
+pub fn check_placement<'a>(
+    grid: &WorldGrid,
+    others: impl Iterator<Item = (Entity, &'a InteractableObject, &'a WorldPosition)>,
+    object: &InteractableObject,
+    position: &WorldPosition,
+    moving: Option<Entity>,
+) -> Result<(), PlacementError> {
+    let footprint = object.footprint_tiles(position);
+
+    if !footprint.iter().all(|&tile| grid.is_free_for(tile, moving)) {
+        return Err(PlacementError::Occupied);
+    }
+
+    let slots_free = object
+        .slot_tiles(position.0)
+        .into_iter()
+        .all(|tile| !footprint.contains(&tile) && grid.is_free_for(tile, moving));
+
+    if !slots_free {
+        return Err(PlacementError::SlotBlocked);
+    }
+
+    for (entity, other, other_position) in others {
+        if Some(entity) == moving {
+            continue;
+        }
+
+        let blocks_slot = other
+            .slot_tiles(other_position.0)
+            .iter()
+            .any(|tile| footprint.contains(tile));
+
+        if blocks_slot {
+            return Err(PlacementError::BlocksSlot);
+        }
+    }
+
+    Ok(())
+}
+
+fn rotate_object_sprites(
+    mut objects: Query<(&InteractableObject, &mut Transform), Changed<InteractableObject>>,
+) {
+    for (object, mut transform) in objects.iter_mut() {
+        transform.rotation = Quat::from_rotation_z(object.rotation.radians());
+    }
+}
+
+pub struct ObjectPlugin;
+
+impl Plugin for ObjectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, rotate_object_sprites);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{IVec2, UVec2};
+
+    use crate::test_utils::definition;
+
+    use super::*;
+
+    fn object(footprint: UVec2, slots: Vec<Vec2>) -> InteractableObject {
+        InteractableObject {
+            definition: Arc::new(ObjectDefinition {
+                footprint,
+                interaction_slots: slots,
+                ..definition("Thing")
+            }),
+            rotation: Rotation::None,
+        }
+    }
+
+    #[test]
+    fn objects_cannot_overlap() {
+        let mut grid = WorldGrid::new(IVec2::ZERO, UVec2::new(4, 4));
+        let existing = Entity::from_raw(1);
+        grid.occupy(existing, [TilePosition::new(1, 1)]);
+
+        let thing = object(UVec2::new(1, 2), vec![Vec2::new(-1.0, 0.0)]);
+        let position = WorldPosition(Vec2::new(1.0, 0.0));
+
+        assert_eq!(
+            check_placement(&grid, std::iter::empty(), &thing, &position, None),
+            Err(PlacementError::Occupied)
+        );
+        assert_eq!(
+            check_placement(&grid, std::iter::empty(), &thing, &position, Some(existing)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn objects_need_room_to_be_used() {
+        let grid = WorldGrid::new(IVec2::ZERO, UVec2::new(4, 4));
+        let thing = object(UVec2::ONE, vec![Vec2::new(0.0, -1.0)]);
+
+        let against_the_edge = WorldPosition(Vec2::new(2.0, 0.0));
+        assert_eq!(
+            check_placement(&grid, std::iter::empty(), &thing, &against_the_edge, None),
+            Err(PlacementError::SlotBlocked)
+        );
+
+        // Turned around, the slot is on the other side
+        let turned = InteractableObject {
+            rotation: Rotation::Half,
+            ..thing
+        };
+        assert_eq!(
+            check_placement(&grid, std::iter::empty(), &turned, &against_the_edge, None),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn objects_cannot_block_other_objects() {
+        let mut grid = WorldGrid::new(IVec2::ZERO, UVec2::new(4, 4));
+        let toilet = object(UVec2::ONE, vec![Vec2::new(0.0, -1.0)]);
+        let toilet_position = WorldPosition(Vec2::new(1.0, 2.0));
+        let toilet_entity = Entity::from_raw(1);
+        grid.occupy(toilet_entity, toilet.footprint_tiles(&toilet_position));
+
+        let others = [(toilet_entity, &toilet, &toilet_position)];
+        let thing = object(UVec2::ONE, vec![Vec2::new(1.0, 0.0)]);
+
+        assert_eq!(
+            check_placement(
+                &grid,
+                others.into_iter(),
+                &thing,
+                &WorldPosition(Vec2::new(1.0, 1.0)),
+                None
+            ),
+            Err(PlacementError::BlocksSlot)
+        );
+    }
+}
@@ -0,0 +1,447 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+};
+
+use bevy::{math::IVec2, prelude::*};
+
+use crate::{
+    action::advance_action_queues,
+    fella::{Fella, WalkTarget},
+    time::SimulationTick,
+    world::{Direction, TilePosition, WorldGrid, WorldPosition},
+};
+
+// Costs are kept integral so that they can be ordered in the heap
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+// Octile distance, i.e. the cost of the path if nothing was in the way
+fn estimate_cost(from: TilePosition, to: TilePosition) -> u32 {
+    let difference = (to.0 - from.0).abs();
+    let diagonal = difference.x.min(difference.y) as u32;
+    let straight = difference.x.max(difference.y) as u32 - diagonal;
+
+    diagonal * DIAGONAL_COST + straight * STRAIGHT_COST
+}
+
+fn horizontal_direction(offset: IVec2) -> Direction {
+    if offset.x > 0 {
+        Direction::East
+    } else {
+        Direction::West
+    }
+}
+
+fn vertical_direction(offset: IVec2) -> Direction {
+    if offset.y > 0 {
+        Direction::North
+    } else {
+        Direction::South
+    }
+}
+
+// Whether a fella can get from one tile to its neighbour. Diagonal steps need both
+// tiles beside them to be free and no walls in between, so that fellas don't
+// squeeze past corners of furniture or the ends of walls.
+fn can_step(grid: &WorldGrid, from: TilePosition, offset: IVec2) -> bool {
+    let to = TilePosition(from.0 + offset);
+
+    if !grid.is_walkable(to) {
+        return false;
+    }
+
+    if offset.y == 0 {
+        return grid.can_cross(from, horizontal_direction(offset));
+    }
+
+    if offset.x == 0 {
+        return grid.can_cross(from, vertical_direction(offset));
+    }
+
+    let side_x = TilePosition(from.0 + IVec2::new(offset.x, 0));
+    let side_y = TilePosition(from.0 + IVec2::new(0, offset.y));
+
+    grid.is_walkable(side_x)
+        && grid.is_walkable(side_y)
+        && grid.can_cross(from, horizontal_direction(offset))
+        && grid.can_cross(from, vertical_direction(offset))
+        && grid.can_cross(side_x, vertical_direction(offset))
+        && grid.can_cross(side_y, horizontal_direction(offset))
+}
+
+// Finds the cheapest route between two tiles.
+// The returned tiles don't include the start, but do include the goal.
+pub fn find_path(
+    grid: &WorldGrid,
+    start: TilePosition,
+    goal: TilePosition,
+) -> Option<Vec<TilePosition>> {
+    if start == goal {
+        return Some(Vec::new());
+    }
+
+    // The start doesn't have to be walkable, so fellas can always get unstuck
+    if !grid.is_walkable(goal) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<TilePosition, TilePosition> = HashMap::new();
+    let mut costs: HashMap<TilePosition, u32> = HashMap::new();
+
+    costs.insert(start, 0);
+    open.push(Reverse((
+        estimate_cost(start, goal),
+        0,
+        start.0.x,
+        start.0.y,
+    )));
+
+    while let Some(Reverse((_, cost, x, y))) = open.pop() {
+        let current = TilePosition::new(x, y);
+
+        if current == goal {
+            let mut path = vec![goal];
+            let mut tile = goal;
+
+            while let Some(&previous) = came_from.get(&tile) {
+                if previous == start {
+                    break;
+                }
+
+                path.push(previous);
+                tile = previous;
+            }
+
+            path.reverse();
+            return Some(path);
+        }
+
+        // Already reached this tile in a cheaper way
+        if costs.get(&current).is_some_and(|&best| cost > best) {
+            continue;
+        }
+
+        for offset in NEIGHBOURS {
+            if !can_step(grid, current, offset) {
+                continue;
+            }
+
+            let next = TilePosition(current.0 + offset);
+            let is_diagonal = offset.x != 0 && offset.y != 0;
+            let step_cost = if is_diagonal {
+                DIAGONAL_COST
+            } else {
+                STRAIGHT_COST
+            };
+            let next_cost = cost + step_cost;
+
+            if costs.get(&next).is_some_and(|&best| next_cost >= best) {
+                continue;
+            }
+
+            costs.insert(next, next_cost);
+            came_from.insert(next, current);
+
+            let priority = next_cost + estimate_cost(next, goal);
+            open.push(Reverse((priority, next_cost, next.0.x, next.0.y)));
+        }
+    }
+
+    None
+}
+
+// Points a fella walks through on the way to their walk target
+#[derive(Component, Clone, Debug, Default)]
+pub struct Path {
+    pub waypoints: VecDeque<Vec2>,
+}
+
+// Sent when there's no way to get to a fella's walk target
+#[derive(Event, Clone, Debug)]
+pub struct RouteFailed {
+    pub fella: Entity,
+    pub target: Vec2,
+}
+
+pub fn plan_paths(
+    grid: Res<WorldGrid>,
+    mut fellas: Query<(Entity, Ref<WalkTarget>, &WorldPosition, &mut Path), With<Fella>>,
+    mut route_failed_events: EventWriter<RouteFailed>,
+) {
+    for (fella, walk_target, world_position, mut path) in fellas.iter_mut() {
+        // Any change to the grid could block the current path
+        if !walk_target.is_changed() && !grid.is_changed() {
+            continue;
+        }
+
+        // Already there
+        if walk_target.target.distance(world_position.0) < 0.1 {
+            path.waypoints.clear();
+            continue;
+        }
+
+        let start = TilePosition::from_world(world_position);
+        let goal = TilePosition::from_world(&WorldPosition(walk_target.target));
+
+        let Some(tiles) = find_path(&grid, start, goal) else {
+            path.waypoints.clear();
+            route_failed_events.send(RouteFailed {
+                fella,
+                target: walk_target.target,
+            });
+
+            continue;
+        };
+
+        // Walk through the middle of each tile, but end up exactly on the target
+        path.waypoints = tiles.iter().map(|tile| tile.center().0).collect();
+        path.waypoints.pop_back();
+        path.waypoints.push_back(walk_target.target);
+    }
+}
+
+// Fellas that can't get anywhere just stay where they are
+fn stop_on_route_failed(
+    mut route_failed_events: EventReader<RouteFailed>,
+    mut fellas: Query<(&mut WalkTarget, &WorldPosition)>,
+) {
+    for event in route_failed_events.read() {
+        let Ok((mut walk_target, world_position)) = fellas.get_mut(event.fella) else {
+            continue;
+        };
+
+        // The fella might have been given somewhere else to go in the meantime
+        if walk_target.target == event.target {
+            walk_target.target = world_position.0;
+        }
+    }
+}
+
+pub struct PathfindingPlugin;
+
+impl Plugin for PathfindingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RouteFailed>().add_systems(
+            SimulationTick,
+            (
+                plan_paths.after(advance_action_queues),
+                // Before planning, so that a route failing again is only ever seen
+                // by the action queues first
+                stop_on_route_failed
+                    .after(advance_action_queues)
+                    .before(plan_paths),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::UVec2;
+
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        action::{ActionCancelled, ActionPlugin, ActionQueue, CancelReason},
+        object::InteractableObject,
+        test_utils::{action, definition},
+        time::SimulationTime,
+        utility::{FellaAction, ObjectDefinition},
+        world::{Edge, Rotation},
+    };
+
+    // Grid with some tiles taken up by furniture
+    fn grid_with_blocked(size: UVec2, blocked: &[(i32, i32)]) -> WorldGrid {
+        let mut grid = WorldGrid::new(IVec2::ZERO, size);
+
+        for &(x, y) in blocked {
+            grid.occupy(Entity::PLACEHOLDER, [TilePosition::new(x, y)]);
+        }
+
+        grid
+    }
+
+    #[test]
+    fn open_grid_uses_diagonals() {
+        let grid = grid_with_blocked(UVec2::new(4, 4), &[]);
+        let path = find_path(&grid, TilePosition::new(0, 0), TilePosition::new(3, 3)).unwrap();
+
+        assert_eq!(
+            path,
+            vec![
+                TilePosition::new(1, 1),
+                TilePosition::new(2, 2),
+                TilePosition::new(3, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn path_goes_around_furniture() {
+        // Furniture down the middle with a gap at the top
+        let grid = grid_with_blocked(UVec2::new(3, 3), &[(1, 0), (1, 1)]);
+        let path = find_path(&grid, TilePosition::new(0, 0), TilePosition::new(2, 0)).unwrap();
+
+        assert!(path.contains(&TilePosition::new(1, 2)));
+        assert_eq!(path.last(), Some(&TilePosition::new(2, 0)));
+    }
+
+    #[test]
+    fn diagonals_do_not_cut_corners() {
+        let grid = grid_with_blocked(UVec2::new(2, 2), &[(1, 0)]);
+        let path = find_path(&grid, TilePosition::new(0, 0), TilePosition::new(1, 1)).unwrap();
+
+        assert_eq!(path, vec![TilePosition::new(0, 1), TilePosition::new(1, 1)]);
+    }
+
+    #[test]
+    fn walled_off_goal_is_unreachable() {
+        let grid = grid_with_blocked(UVec2::new(3, 3), &[(1, 0), (1, 1), (1, 2)]);
+
+        assert_eq!(
+            find_path(&grid, TilePosition::new(0, 0), TilePosition::new(2, 2)),
+            None
+        );
+        assert_eq!(
+            find_path(&grid, TilePosition::new(0, 0), TilePosition::new(1, 1)),
+            None
+        );
+    }
+
+    #[test]
+    fn fellas_can_walk_off_blocked_tiles() {
+        let grid = grid_with_blocked(UVec2::new(2, 1), &[(0, 0)]);
+        let path = find_path(&grid, TilePosition::new(0, 0), TilePosition::new(1, 0)).unwrap();
+
+        assert_eq!(path, vec![TilePosition::new(1, 0)]);
+    }
+
+    #[test]
+    fn walls_on_edges_block_paths() {
+        // A wall between the two columns with a gap at the top
+        let mut grid = grid_with_blocked(UVec2::new(2, 3), &[]);
+        grid.add_wall(IVec2::new(1, 0), IVec2::new(1, 2));
+
+        let path = find_path(&grid, TilePosition::new(0, 0), TilePosition::new(1, 0)).unwrap();
+
+        assert_eq!(
+            path,
+            vec![
+                TilePosition::new(0, 1),
+                TilePosition::new(0, 2),
+                TilePosition::new(1, 2),
+                TilePosition::new(1, 1),
+                TilePosition::new(1, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn diagonals_do_not_slip_past_wall_ends() {
+        let mut grid = grid_with_blocked(UVec2::new(2, 2), &[]);
+        grid.add_wall(IVec2::new(1, 0), IVec2::new(1, 1));
+
+        let path = find_path(&grid, TilePosition::new(0, 0), TilePosition::new(1, 1)).unwrap();
+
+        assert_eq!(path, vec![TilePosition::new(0, 1), TilePosition::new(1, 1)]);
+    }
+
+    #[test]
+    fn fellas_walk_through_doors() {
+        let mut grid = grid_with_blocked(UVec2::new(3, 3), &[]);
+        grid.add_wall(IVec2::new(0, 1), IVec2::new(3, 1));
+        grid.set_edge(TilePosition::new(2, 0), Direction::North, Some(Edge::Door));
+
+        let path = find_path(&grid, TilePosition::new(0, 0), TilePosition::new(0, 2)).unwrap();
+
+        assert!(path.contains(&TilePosition::new(2, 0)));
+        assert!(path.contains(&TilePosition::new(2, 1)));
+
+        grid.add_wall(IVec2::new(2, 1), IVec2::new(3, 1));
+        assert_eq!(
+            find_path(&grid, TilePosition::new(0, 0), TilePosition::new(0, 2)),
+            None
+        );
+    }
+
+    #[test]
+    fn walled_off_slot_cancels_the_action() {
+        let mut app = App::new();
+        app.add_plugins((ActionPlugin, PathfindingPlugin))
+            .insert_resource(SimulationTime::default())
+            // Furniture down the middle, with the bed on the other side
+            .insert_resource(grid_with_blocked(
+                UVec2::new(3, 3),
+                &[(1, 0), (1, 1), (1, 2)],
+            ));
+
+        let bed = Arc::new(ObjectDefinition {
+            actions: vec![action("Sleep")],
+            ..definition("Bed")
+        });
+        let object = app
+            .world
+            .spawn((
+                InteractableObject {
+                    definition: bed.clone(),
+                    rotation: Rotation::None,
+                },
+                WorldPosition(Vec2::new(2.0, 1.0)),
+            ))
+            .id();
+
+        let mut queue = ActionQueue::default();
+        queue.push(FellaAction::UseObject(object, bed, 0));
+        let fella = app
+            .world
+            .spawn((
+                Fella,
+                queue,
+                WalkTarget {
+                    target: Vec2::ZERO,
+                    assigned_at: SimulationTime::default(),
+                },
+                WorldPosition(Vec2::ZERO),
+                Path::default(),
+            ))
+            .id();
+
+        for _ in 0..5 {
+            app.world.run_schedule(SimulationTick);
+        }
+
+        let cancelled: Vec<CancelReason> = app
+            .world
+            .resource::<Events<ActionCancelled>>()
+            .get_reader()
+            .read(app.world.resource::<Events<ActionCancelled>>())
+            .map(|event| event.reason)
+            .collect();
+
+        assert_eq!(cancelled, vec![CancelReason::Unreachable]);
+        assert!(app
+            .world
+            .get::<ActionQueue>(fella)
+            .unwrap()
+            .current()
+            .is_none());
+        assert_eq!(
+            app.world.get::<WalkTarget>(fella).unwrap().target,
+            Vec2::ZERO
+        );
+    }
+}
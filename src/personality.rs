@@ -0,0 +1,72 @@
+// Personality traits from the GDC talk in the readme. Each trait goes from -1.0 to 1.0,
+// e.g. neat = -1.0 is as sloppy as it gets and neat = 1.0 is a neat freak.
+
+use bevy::ecs::component::Component;
+use serde::{Deserialize, Serialize};
+
+use crate::fella::BasicMotive;
+
+#[derive(Component, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Personality {
+    // Neat / sloppy
+    pub neat: f32,
+    // Outgoing / shy
+    pub outgoing: f32,
+    // Active / lazy
+    pub active: f32,
+    // Playful / serious
+    pub playful: f32,
+    // Nice / grouchy
+    pub nice: f32,
+}
+
+impl Personality {
+    // How strongly the fella feels a motive compared to an average fella.
+    // Scales both how fast the motive decays and how much actions affecting it are worth.
+    pub fn motive_sensitivity(&self, motive: BasicMotive) -> f32 {
+        let sensitivity = match motive {
+            BasicMotive::Hygiene => 1.0 + 0.5 * self.neat,
+            BasicMotive::Environment => 1.0 + 0.6 * self.neat,
+            BasicMotive::Social => 1.0 + 0.6 * self.outgoing + 0.2 * self.nice,
+            BasicMotive::Fun => 1.0 + 0.6 * self.playful - 0.2 * self.active,
+            BasicMotive::Energy => 1.0 - 0.3 * self.active,
+            BasicMotive::Comfort => 1.0 - 0.5 * self.active,
+            BasicMotive::Hunger => 1.0 + 0.2 * self.active,
+            BasicMotive::Bathroom => 1.0,
+        };
+
+        // Nobody is completely indifferent to a motive
+        sensitivity.max(0.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn average_personality_is_neutral() {
+        let personality = Personality::default();
+
+        for motive in crate::fella::ALL_MOTIVES {
+            assert_eq!(personality.motive_sensitivity(motive), 1.0);
+        }
+    }
+
+    #[test]
+    fn neat_fellas_care_more_about_hygiene() {
+        let neat = Personality {
+            neat: 1.0,
+            ..Personality::default()
+        };
+        let sloppy = Personality {
+            neat: -1.0,
+            ..Personality::default()
+        };
+
+        assert!(
+            neat.motive_sensitivity(BasicMotive::Hygiene)
+                > sloppy.motive_sensitivity(BasicMotive::Hygiene)
+        );
+    }
+}
@@ -4,10 +4,13 @@ use bevy::prelude::*;
 use bevy::sprite::Anchor;
 use bevy::{math::Rect, transform::components::GlobalTransform};
 
-use crate::camera::MainCamera;
+use crate::{
+    camera::MainCamera,
+    fella::{Fella, SelectedFella},
+};
 
 #[derive(Component, Debug, Clone)]
-struct SpriteRect(Rect);
+pub struct SpriteRect(Rect);
 
 fn calculate_sprite_rects(
     texture_atlases: Res<Assets<TextureAtlas>>,
@@ -62,40 +65,84 @@ fn add_sprite_rect(
 #[derive(Component, Debug, Clone)]
 pub struct Pickable;
 
+// Where the cursor is in the 2D scene, in pixels
+pub fn cursor_world_position(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    let cursor_position = window.cursor_position()?;
+    camera.viewport_to_world_2d(camera_transform, cursor_position)
+}
+
 #[derive(Event, Debug, Clone)]
 pub struct OnPickEvent(pub Entity);
 
-fn handle_on_click(
+// Sent when a pickable sprite is right-clicked
+#[derive(Event, Debug, Clone)]
+pub struct OnContextPickEvent(pub Entity);
+
+pub fn handle_on_click(
     buttons: Res<Input<MouseButton>>,
     window: Query<&Window>,
     camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
     sprites: Query<(Entity, &SpriteRect), With<Pickable>>,
     mut pick_events: EventWriter<OnPickEvent>,
+    mut context_pick_events: EventWriter<OnContextPickEvent>,
 ) {
     let clicking = buttons.just_pressed(MouseButton::Left);
+    let context_clicking = buttons.just_pressed(MouseButton::Right);
 
-    if !clicking {
+    if !clicking && !context_clicking {
         return;
     }
 
-    let window = window.single();
     let (camera, camera_transform) = camera.single();
 
-    let Some(cursor_position) = window.cursor_position() else {
-        return;
-    };
-
-    let cursor_world_position = camera.viewport_to_world_2d(camera_transform, cursor_position);
-
-    let Some(cursor_world_position) = cursor_world_position else {
+    let Some(cursor_world_position) =
+        cursor_world_position(window.single(), camera, camera_transform)
+    else {
         return;
     };
 
     for (entity, sprite_rect) in sprites.iter() {
-        if sprite_rect.0.contains(cursor_world_position) {
+        if !sprite_rect.0.contains(cursor_world_position) {
+            continue;
+        }
+
+        if clicking {
             println!("Clicked {:?}", entity);
             pick_events.send(OnPickEvent(entity));
         }
+
+        if context_clicking {
+            println!("Right-clicked {:?}", entity);
+            context_pick_events.send(OnContextPickEvent(entity));
+        }
+    }
+}
+
+fn select_fella(
+    mut selected_fella: ResMut<SelectedFella>,
+    mut select_fella_events: EventReader<OnPickEvent>,
+    mut fellas: Query<(Entity, &mut TextureAtlasSprite), With<Fella>>,
+) {
+    for event in select_fella_events.read() {
+        // Objects can be picked too
+        if !fellas.contains(event.0) {
+            continue;
+        }
+
+        selected_fella.0 = Some(event.0);
+        println!("Selected fella: {:?}", event.0);
+
+        for (fella_entity, mut sprite) in fellas.iter_mut() {
+            sprite.color = if fella_entity == event.0 {
+                Color::RED
+            } else {
+                Color::WHITE
+            };
+        }
     }
 }
 
@@ -103,13 +150,16 @@ pub struct MyPickingPlugin;
 
 impl Plugin for MyPickingPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_event::<OnPickEvent>().add_systems(
-            Update,
-            (
-                calculate_sprite_rects.after(add_sprite_rect),
-                add_sprite_rect,
-                handle_on_click.after(calculate_sprite_rects),
-            ),
-        );
+        app.add_event::<OnPickEvent>()
+            .add_event::<OnContextPickEvent>()
+            .add_systems(
+                Update,
+                (
+                    calculate_sprite_rects.after(add_sprite_rect),
+                    add_sprite_rect,
+                    handle_on_click.after(calculate_sprite_rects),
+                    select_fella.after(handle_on_click),
+                ),
+            );
     }
 }
@@ -0,0 +1,103 @@
+// All randomness in the simulation comes from here, so a run can be replayed from its seed
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::fella::FellaId;
+
+#[derive(Resource, Clone, Debug)]
+pub struct SimRng {
+    seed: u64,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        SimRng { seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Random numbers for whatever goes by the given name. The same seed and name always
+    // give the same numbers, no matter what else draws from the simulation's randomness.
+    pub fn stream(&self, name: &str) -> StdRng {
+        StdRng::seed_from_u64(self.seed ^ stable_hash(name))
+    }
+}
+
+// FNV-1a, which unlike the standard library's hasher is guaranteed never to change
+fn stable_hash(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
+
+// Seed given on the command line with --seed N, if any
+pub fn seed_from_args(args: &[String]) -> Option<u64> {
+    args.windows(2)
+        .find(|pair| pair[0] == "--seed")
+        .and_then(|pair| pair[1].parse().ok())
+}
+
+// Each fella draws from their own stream, so adding a fella doesn't change what the others do
+#[derive(Component)]
+pub struct FellaRng(pub StdRng);
+
+impl FellaRng {
+    pub fn new(sim_rng: &SimRng, id: FellaId) -> Self {
+        FellaRng(sim_rng.stream(&format!("fella {}", id.0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draws(rng: &mut StdRng) -> Vec<u32> {
+        (0..8).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn same_seed_and_name_give_the_same_numbers() {
+        let a = SimRng::new(42).stream("Felix Fella");
+        let b = SimRng::new(42).stream("Felix Fella");
+
+        assert_eq!(draws(&mut a.clone()), draws(&mut b.clone()));
+    }
+
+    #[test]
+    fn streams_differ_by_seed_and_name() {
+        let felix = draws(&mut SimRng::new(42).stream("Felix Fella"));
+        let fiona = draws(&mut SimRng::new(42).stream("Fiona Fella"));
+        let other_seed = draws(&mut SimRng::new(43).stream("Felix Fella"));
+
+        assert_ne!(felix, fiona);
+        assert_ne!(felix, other_seed);
+    }
+
+    #[test]
+    fn seed_can_be_given_on_the_command_line() {
+        let args: Vec<String> = ["--headless", "--seed", "7"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+
+        assert_eq!(seed_from_args(&args), Some(7));
+        assert_eq!(seed_from_args(&args[..2]), None);
+    }
+
+    #[test]
+    fn every_fella_gets_their_own_stream() {
+        let sim_rng = SimRng::new(42);
+        let first = draws(&mut FellaRng::new(&sim_rng, FellaId(0)).0);
+        let second = draws(&mut FellaRng::new(&sim_rng, FellaId(1)).0);
+
+        assert_eq!(first, draws(&mut FellaRng::new(&sim_rng, FellaId(0)).0));
+        assert_ne!(first, second);
+    }
+}
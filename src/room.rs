@@ -0,0 +1,192 @@
+// Rooms are areas of the lot closed off by walls and doors
+
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::world::{Direction, TilePosition, WorldGrid, WorldPosition};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RoomId(pub usize);
+
+#[derive(Clone, Debug)]
+pub struct Room {
+    pub id: RoomId,
+    pub tiles: Vec<TilePosition>,
+    // Rooms that reach the edge of the lot aren't really rooms
+    pub outdoors: bool,
+}
+
+impl Room {
+    pub fn size(&self) -> usize {
+        self.tiles.len()
+    }
+}
+
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Rooms {
+    rooms: Vec<Room>,
+    room_of_tile: HashMap<TilePosition, RoomId>,
+}
+
+impl Rooms {
+    pub fn get(&self, id: RoomId) -> Option<&Room> {
+        self.rooms.get(id.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Room> {
+        self.rooms.iter()
+    }
+
+    pub fn room_of_tile(&self, position: TilePosition) -> Option<&Room> {
+        self.room_of_tile
+            .get(&position)
+            .and_then(|&id| self.get(id))
+    }
+
+    // The room something in the world is in
+    pub fn room_at(&self, position: &WorldPosition) -> Option<&Room> {
+        self.room_of_tile(TilePosition::from_world(position))
+    }
+}
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+// Flood fills the grid, only spreading across edges without walls or doors on them
+pub fn detect_rooms(grid: &WorldGrid) -> Rooms {
+    let mut rooms = Rooms::default();
+
+    for start in grid.positions() {
+        if rooms.room_of_tile.contains_key(&start) {
+            continue;
+        }
+
+        let id = RoomId(rooms.rooms.len());
+        let mut room = Room {
+            id,
+            tiles: Vec::new(),
+            outdoors: false,
+        };
+
+        let mut open = VecDeque::from([start]);
+        rooms.room_of_tile.insert(start, id);
+
+        while let Some(position) = open.pop_front() {
+            room.tiles.push(position);
+
+            for side in DIRECTIONS {
+                if grid.edge(position, side).is_some() {
+                    continue;
+                }
+
+                let next = TilePosition(position.0 + side.offset());
+
+                if !grid.contains(next) {
+                    room.outdoors = true;
+                    continue;
+                }
+
+                if rooms.room_of_tile.contains_key(&next) {
+                    continue;
+                }
+
+                rooms.room_of_tile.insert(next, id);
+                open.push_back(next);
+            }
+        }
+
+        rooms.rooms.push(room);
+    }
+
+    rooms
+}
+
+fn update_rooms(
+    grid: Res<WorldGrid>,
+    mut rooms: ResMut<Rooms>,
+    mut generation: Local<Option<u32>>,
+) {
+    // Furniture moving around doesn't change the rooms, only walls and doors do
+    if *generation == Some(grid.edge_generation()) {
+        return;
+    }
+
+    *generation = Some(grid.edge_generation());
+    *rooms = detect_rooms(&grid);
+
+    for room in rooms.iter().filter(|room| !room.outdoors) {
+        println!("Found room {:?} with {} tiles", room.id, room.size());
+    }
+}
+
+pub struct RoomPlugin;
+
+impl Plugin for RoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rooms>()
+            .add_systems(Update, update_rooms);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{IVec2, UVec2};
+
+    use super::*;
+    use crate::world::Edge;
+
+    // A 6x6 lot with a 2x2 room in the middle
+    fn lot_with_room() -> WorldGrid {
+        let mut grid = WorldGrid::new(IVec2::ZERO, UVec2::new(6, 6));
+        grid.add_wall(IVec2::new(2, 2), IVec2::new(4, 2));
+        grid.add_wall(IVec2::new(4, 2), IVec2::new(4, 4));
+        grid.add_wall(IVec2::new(4, 4), IVec2::new(2, 4));
+        grid.add_wall(IVec2::new(2, 4), IVec2::new(2, 2));
+        grid
+    }
+
+    #[test]
+    fn walls_enclose_a_room() {
+        let rooms = detect_rooms(&lot_with_room());
+
+        let inside = rooms.room_of_tile(TilePosition::new(3, 3)).unwrap();
+        let outside = rooms.room_of_tile(TilePosition::new(0, 0)).unwrap();
+
+        assert_ne!(inside.id, outside.id);
+        assert_eq!(inside.size(), 4);
+        assert!(!inside.outdoors);
+        assert!(outside.outdoors);
+        assert_eq!(outside.size(), 32);
+    }
+
+    #[test]
+    fn doors_separate_rooms() {
+        let mut grid = lot_with_room();
+        grid.set_edge(TilePosition::new(2, 2), Direction::South, Some(Edge::Door));
+
+        let rooms = detect_rooms(&grid);
+
+        assert_eq!(rooms.iter().count(), 2);
+    }
+
+    #[test]
+    fn removing_a_wall_merges_rooms() {
+        let mut grid = lot_with_room();
+        grid.set_edge(TilePosition::new(2, 2), Direction::South, None);
+
+        let rooms = detect_rooms(&grid);
+
+        assert_eq!(rooms.iter().count(), 1);
+        assert!(
+            rooms
+                .room_at(&WorldPosition(Vec2::new(3.2, 2.9)))
+                .unwrap()
+                .outdoors
+        );
+    }
+}
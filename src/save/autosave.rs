@@ -0,0 +1,117 @@
+// Saving automatically every in-game midnight, taking turns between a few slots
+
+use std::{path::PathBuf, time::SystemTime};
+
+use bevy::{ecs::system::Command, prelude::*, tasks::IoTaskPool};
+
+use crate::{calendar::Calendar, time::SimulationTime};
+
+use super::{save_world, write_save_file};
+
+pub const AUTOSAVE_SLOTS: usize = 3;
+
+pub fn slot_path(slot: usize) -> PathBuf {
+    PathBuf::from(format!("saves/autosave_{}.ron", slot))
+}
+
+// Slot that hasn't been used yet, or else the one that was written longest ago
+fn pick_slot(last_written: &[Option<SystemTime>]) -> usize {
+    last_written
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, written)| **written)
+        .map_or(0, |(slot, _)| slot)
+}
+
+fn oldest_slot() -> usize {
+    let last_written: Vec<Option<SystemTime>> = (0..AUTOSAVE_SLOTS)
+        .map(|slot| {
+            std::fs::metadata(slot_path(slot))
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect();
+
+    pick_slot(&last_written)
+}
+
+struct Autosave;
+
+impl Command for Autosave {
+    fn apply(self, world: &mut World) {
+        // Taking the snapshot has to happen now, but writing it out can happen whenever
+        let save = save_world(world);
+
+        IoTaskPool::get()
+            .spawn(async move {
+                let path = slot_path(oldest_slot());
+
+                match write_save_file(&path, &save) {
+                    Ok(()) => println!("Autosaved to {}", path.display()),
+                    Err(error) => println!("{}", error),
+                }
+            })
+            .detach();
+    }
+}
+
+// Whether the tick that just ran was the one that made it midnight
+fn is_midnight(time: &SimulationTime, calendar: &Calendar) -> bool {
+    time.get_time(calendar) == 0
+}
+
+// Runs every tick rather than every frame, so that the snapshot is taken right at midnight
+// however many ticks a frame runs. Loading a save doesn't tick the clock, so it doesn't count.
+pub fn autosave_at_midnight(
+    mut commands: Commands,
+    time: Res<SimulationTime>,
+    calendar: Res<Calendar>,
+) {
+    if is_midnight(&time, &calendar) {
+        commands.add(Autosave);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn unused_slots_are_filled_first() {
+        let earlier = SystemTime::UNIX_EPOCH;
+        let later = earlier + Duration::from_secs(60);
+
+        assert_eq!(pick_slot(&[Some(earlier), None, Some(later)]), 1);
+    }
+
+    #[test]
+    fn every_midnight_is_noticed_on_short_days() {
+        let calendar = Calendar {
+            hours_per_day: 10,
+            ..default()
+        };
+        let mut time = SimulationTime::default();
+        let mut midnights = 0;
+
+        // From noon, a little over two of those days
+        for _ in 0..1300 {
+            time.tick();
+
+            if is_midnight(&time, &calendar) {
+                midnights += 1;
+            }
+        }
+
+        assert_eq!(midnights, 2);
+    }
+
+    #[test]
+    fn oldest_slot_is_overwritten() {
+        let earlier = SystemTime::UNIX_EPOCH;
+        let later = earlier + Duration::from_secs(60);
+
+        assert_eq!(pick_slot(&[Some(later), Some(earlier), Some(later)]), 1);
+    }
+}
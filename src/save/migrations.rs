@@ -0,0 +1,369 @@
+// Older save formats, each upgraded one version at a time until it's the current one.
+// When the format changes, copy the old types and everything they contain into a module here,
+// and add a step to upgrade().
+
+use serde::Deserialize;
+
+use crate::{
+    action::{ActionSource, ActionState},
+    failure::Starving,
+    fella::{BasicMotive, WalkTarget},
+    personality::Personality,
+    time::SimulationTime,
+    world::Rotation,
+};
+
+use super::{LoadError, SaveFile, SavedAction, SavedFella, SavedObject};
+
+// Version 1 didn't keep track of when fellas got their walk target. Everything it saved is
+// copied here as it was, so that changes to the game's own types can't change how it parses.
+mod v1 {
+    use bevy::math::Vec2;
+    use serde::Deserialize;
+
+    // Ticks, possibly part way through one
+    #[derive(Deserialize)]
+    pub struct Time(pub f64);
+
+    #[derive(Deserialize)]
+    pub enum Rotation {
+        None,
+        Quarter,
+        Half,
+        ThreeQuarters,
+    }
+
+    #[derive(Deserialize)]
+    pub struct SavedObject {
+        pub definition: String,
+        pub position: Vec2,
+        pub rotation: Rotation,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Personality {
+        pub neat: f32,
+        pub outgoing: f32,
+        pub active: f32,
+        pub playful: f32,
+        pub nice: f32,
+    }
+
+    #[derive(Deserialize)]
+    pub enum BasicMotive {
+        Hunger,
+        Bathroom,
+        Energy,
+        Hygiene,
+        Social,
+        Fun,
+        Comfort,
+        Environment,
+    }
+
+    #[derive(Deserialize)]
+    pub enum ActionState {
+        Queued,
+        WalkingTo { slot: usize },
+        Performing { slot: usize, started_at: Time },
+    }
+
+    #[derive(Deserialize)]
+    pub enum ActionSource {
+        Autonomous,
+        Player,
+    }
+
+    #[derive(Deserialize)]
+    pub struct SavedAction {
+        pub object: usize,
+        pub action: String,
+        pub state: ActionState,
+        pub source: ActionSource,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Starving {
+        pub since: Time,
+        pub severe: bool,
+    }
+
+    #[derive(Deserialize)]
+    pub struct SaveFile {
+        pub time: Time,
+        pub objects: Vec<SavedObject>,
+        pub fellas: Vec<SavedFella>,
+        #[serde(default)]
+        pub puddles: Vec<Vec2>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct SavedFella {
+        pub name: String,
+        pub sprite: String,
+        pub personality: Personality,
+        pub motives: Vec<(BasicMotive, f32)>,
+        pub position: Vec2,
+        pub walk_target: Vec2,
+        pub actions: Vec<SavedAction>,
+        #[serde(default)]
+        pub passed_out: bool,
+        #[serde(default)]
+        pub starving: Option<Starving>,
+    }
+}
+
+impl From<v1::Time> for SimulationTime {
+    // Only whole ticks count
+    fn from(time: v1::Time) -> Self {
+        SimulationTime::from_ticks(time.0.floor() as u64)
+    }
+}
+
+impl From<v1::Rotation> for Rotation {
+    fn from(rotation: v1::Rotation) -> Self {
+        match rotation {
+            v1::Rotation::None => Rotation::None,
+            v1::Rotation::Quarter => Rotation::Quarter,
+            v1::Rotation::Half => Rotation::Half,
+            v1::Rotation::ThreeQuarters => Rotation::ThreeQuarters,
+        }
+    }
+}
+
+impl From<v1::SavedObject> for SavedObject {
+    fn from(object: v1::SavedObject) -> Self {
+        SavedObject {
+            definition: object.definition,
+            position: object.position,
+            rotation: object.rotation.into(),
+        }
+    }
+}
+
+impl From<v1::Personality> for Personality {
+    fn from(personality: v1::Personality) -> Self {
+        Personality {
+            neat: personality.neat,
+            outgoing: personality.outgoing,
+            active: personality.active,
+            playful: personality.playful,
+            nice: personality.nice,
+        }
+    }
+}
+
+impl From<v1::BasicMotive> for BasicMotive {
+    fn from(motive: v1::BasicMotive) -> Self {
+        match motive {
+            v1::BasicMotive::Hunger => BasicMotive::Hunger,
+            v1::BasicMotive::Bathroom => BasicMotive::Bathroom,
+            v1::BasicMotive::Energy => BasicMotive::Energy,
+            v1::BasicMotive::Hygiene => BasicMotive::Hygiene,
+            v1::BasicMotive::Social => BasicMotive::Social,
+            v1::BasicMotive::Fun => BasicMotive::Fun,
+            v1::BasicMotive::Comfort => BasicMotive::Comfort,
+            v1::BasicMotive::Environment => BasicMotive::Environment,
+        }
+    }
+}
+
+impl From<v1::SavedAction> for SavedAction {
+    fn from(action: v1::SavedAction) -> Self {
+        SavedAction {
+            object: action.object,
+            action: action.action,
+            state: match action.state {
+                v1::ActionState::Queued => ActionState::Queued,
+                v1::ActionState::WalkingTo { slot } => ActionState::WalkingTo { slot },
+                v1::ActionState::Performing { slot, started_at } => ActionState::Performing {
+                    slot,
+                    started_at: started_at.into(),
+                },
+            },
+            source: match action.source {
+                v1::ActionSource::Autonomous => ActionSource::Autonomous,
+                v1::ActionSource::Player => ActionSource::Player,
+            },
+        }
+    }
+}
+
+impl From<v1::Starving> for Starving {
+    fn from(starving: v1::Starving) -> Self {
+        Starving {
+            since: starving.since.into(),
+            severe: starving.severe,
+        }
+    }
+}
+
+// Just enough of a save to tell which version it is
+#[derive(Deserialize)]
+#[serde(rename = "SaveFile")]
+struct SaveHeader {
+    version: u32,
+}
+
+enum VersionedSave {
+    V1(v1::SaveFile),
+    V2(SaveFile),
+}
+
+impl VersionedSave {
+    fn parse(contents: &str) -> Result<Self, LoadError> {
+        let header: SaveHeader = ron::from_str(contents)?;
+
+        match header.version {
+            1 => Ok(VersionedSave::V1(ron::from_str(contents)?)),
+            2 => Ok(VersionedSave::V2(ron::from_str(contents)?)),
+            version => Err(LoadError::UnsupportedVersion(version)),
+        }
+    }
+
+    // Moves the save one version forward
+    fn upgrade(self) -> Self {
+        match self {
+            VersionedSave::V1(save) => VersionedSave::V2(v1_to_v2(save)),
+            current @ VersionedSave::V2(_) => current,
+        }
+    }
+}
+
+// Pretend fellas got their walk target just as the game was saved
+fn v1_to_v2(save: v1::SaveFile) -> SaveFile {
+    let time = SimulationTime::from(save.time);
+
+    let fellas = save
+        .fellas
+        .into_iter()
+        .map(|fella| SavedFella {
+            id: None,
+            name: fella.name,
+            sprite: fella.sprite,
+            personality: fella.personality.into(),
+            motives: fella
+                .motives
+                .into_iter()
+                .map(|(motive, value)| (motive.into(), value))
+                .collect(),
+            position: fella.position,
+            walk_target: WalkTarget {
+                target: fella.walk_target,
+                assigned_at: time.clone(),
+            },
+            actions: fella.actions.into_iter().map(SavedAction::from).collect(),
+            passed_out: fella.passed_out,
+            starving: fella.starving.map(Starving::from),
+        })
+        .collect();
+
+    SaveFile {
+        version: 2,
+        time,
+        objects: save.objects.into_iter().map(SavedObject::from).collect(),
+        fellas,
+        puddles: save.puddles,
+    }
+}
+
+pub fn parse_save(contents: &str) -> Result<SaveFile, LoadError> {
+    let mut save = VersionedSave::parse(contents)?;
+
+    loop {
+        match save {
+            VersionedSave::V2(current) => return Ok(current),
+            older => save = older.upgrade(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        path::Path,
+        sync::Arc,
+    };
+
+    use bevy::prelude::With;
+
+    use crate::{
+        catalog::ObjectCatalog,
+        fella::FellaId,
+        rng::FellaRng,
+        save::{load_world, SAVE_VERSION},
+        test_utils::world_with_assets,
+        utility::ObjectDefinition,
+    };
+
+    use super::*;
+
+    fn basic_definitions() -> HashMap<String, Arc<ObjectDefinition>> {
+        let catalog: ObjectCatalog =
+            ron::from_str(include_str!("../../assets/objects/basic.objects.ron")).unwrap();
+
+        catalog
+            .objects
+            .iter()
+            .map(|definition| (definition.name.clone(), definition.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn old_saves_keep_loading() {
+        let definitions = basic_definitions();
+
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/saves");
+        let mut loaded = 0;
+
+        for entry in std::fs::read_dir(fixtures).unwrap() {
+            let path = entry.unwrap().path();
+            let contents = std::fs::read_to_string(&path).unwrap();
+
+            let save = parse_save(&contents)
+                .unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+            assert_eq!(save.version, SAVE_VERSION);
+
+            load_world(&mut world_with_assets(), &save, &definitions)
+                .unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+
+            loaded += 1;
+        }
+
+        // One for every version so far
+        assert!(loaded >= SAVE_VERSION);
+    }
+
+    #[test]
+    fn walk_targets_from_version_1_are_assigned_at_save_time() {
+        let save =
+            parse_save(include_str!("../../tests/fixtures/saves/v1_two_fellas.ron")).unwrap();
+
+        for fella in save.fellas.iter() {
+            assert_eq!(fella.walk_target.assigned_at, save.time);
+        }
+    }
+
+    #[test]
+    fn fellas_from_before_ids_get_one_each() {
+        let save =
+            parse_save(include_str!("../../tests/fixtures/saves/v1_two_fellas.ron")).unwrap();
+        let mut world = world_with_assets();
+        load_world(&mut world, &save, &basic_definitions()).unwrap();
+
+        let ids: HashSet<FellaId> = world
+            .query_filtered::<&FellaId, With<FellaRng>>()
+            .iter(&world)
+            .copied()
+            .collect();
+
+        assert_eq!(ids.len(), 2);
+    }
+
+    #[test]
+    fn saves_from_the_future_are_refused() {
+        let result = parse_save("(version: 99, time: (0.0), objects: [], fellas: [])");
+        assert!(matches!(result, Err(LoadError::UnsupportedVersion(99))));
+    }
+}
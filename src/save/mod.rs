@@ -0,0 +1,637 @@
+// Saving the whole simulation to a file and loading it back
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::{ecs::system::Command, prelude::*};
+use bevy_aseprite::Aseprite;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+mod autosave;
+mod migrations;
+
+use autosave::{autosave_at_midnight, AUTOSAVE_SLOTS};
+
+use crate::{
+    action::{ActionQueue, ActionSource, ActionState, QueuedAction},
+    catalog::{ObjectCatalog, PlaceCatalogObjects},
+    edit::EditHistory,
+    failure::{puddle_bundle, PassedOut, Puddle, Starving},
+    fella::{
+        fella_bundle, BasicMotive, BasicMotives, Fella, FellaId, Named, SelectedFella, WalkTarget,
+        ALL_MOTIVES,
+    },
+    object::{object_bundle, InteractableObject},
+    personality::Personality,
+    rng::SimRng,
+    time::{advance_time, SimulationTick, SimulationTime},
+    utility::{FellaAction, ObjectDefinition},
+    world::{Rotation, WorldPosition},
+};
+
+// Bumped whenever the format changes, with a migration from the previous version
+pub const SAVE_VERSION: u32 = 2;
+
+const QUICKSAVE_PATH: &str = "saves/quicksave.ron";
+
+// Every save the player can load, with a name to show for it
+pub fn save_slots() -> Vec<(String, PathBuf)> {
+    let mut slots = vec![(String::from("Quicksave"), PathBuf::from(QUICKSAVE_PATH))];
+
+    slots.extend(
+        (0..AUTOSAVE_SLOTS)
+            .map(|slot| (format!("Autosave {}", slot + 1), autosave::slot_path(slot))),
+    );
+
+    slots
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub time: SimulationTime,
+    pub objects: Vec<SavedObject>,
+    pub fellas: Vec<SavedFella>,
+    #[serde(default)]
+    pub puddles: Vec<Vec2>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedObject {
+    // Name of the definition in the object catalogs
+    pub definition: String,
+    pub position: Vec2,
+    pub rotation: Rotation,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedFella {
+    // Saves from before fellas had ids don't have one, see load_world
+    #[serde(default)]
+    pub id: Option<FellaId>,
+    pub name: String,
+    pub sprite: String,
+    pub personality: Personality,
+    pub motives: Vec<(BasicMotive, f32)>,
+    pub position: Vec2,
+    pub walk_target: WalkTarget,
+    pub actions: Vec<SavedAction>,
+    #[serde(default)]
+    pub passed_out: bool,
+    #[serde(default)]
+    pub starving: Option<Starving>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedAction {
+    // Index into SaveFile::objects, since entities don't survive a save
+    pub object: usize,
+    // Name of the action in the object's definition
+    pub action: String,
+    pub state: ActionState,
+    pub source: ActionSource,
+}
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("Could not write save file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not serialize save file: {0}")]
+    Ron(#[from] ron::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("Could not read save file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse save file: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("Save file version {0} is not supported")]
+    UnsupportedVersion(u32),
+    #[error("Unknown object in save file: {0}")]
+    UnknownObject(String),
+    #[error("Unknown action in save file: {object} has no {action}")]
+    UnknownAction { object: String, action: String },
+    #[error("Unknown interaction slot in save file: {object} has no slot {slot}")]
+    UnknownSlot { object: String, slot: usize },
+}
+
+pub fn save_world(world: &mut World) -> SaveFile {
+    let mut objects: Vec<_> = world
+        .query::<(Entity, &InteractableObject, &WorldPosition)>()
+        .iter(world)
+        .collect();
+    objects.sort_by_key(|(entity, _, _)| *entity);
+
+    let object_indices: HashMap<Entity, usize> = objects
+        .iter()
+        .enumerate()
+        .map(|(index, (entity, _, _))| (*entity, index))
+        .collect();
+
+    let saved_objects = objects
+        .iter()
+        .map(|(_, object, world_position)| SavedObject {
+            definition: object.definition.name.clone(),
+            position: world_position.0,
+            rotation: object.rotation,
+        })
+        .collect();
+
+    let mut fellas: Vec<_> = world
+        .query_filtered::<(
+            Entity,
+            &FellaId,
+            &Named,
+            &Handle<Aseprite>,
+            &Personality,
+            &BasicMotives,
+            &WorldPosition,
+            &WalkTarget,
+            &ActionQueue,
+            Has<PassedOut>,
+            Option<&Starving>,
+        ), With<Fella>>()
+        .iter(world)
+        .collect();
+    fellas.sort_by_key(|fella| fella.0);
+
+    let saved_fellas = fellas
+        .into_iter()
+        .map(
+            |(
+                _,
+                id,
+                name,
+                sprite,
+                personality,
+                motives,
+                world_position,
+                walk_target,
+                queue,
+                passed_out,
+                starving,
+            )| SavedFella {
+                id: Some(*id),
+                name: name.0.clone(),
+                sprite: sprite
+                    .path()
+                    .map(|path| path.to_string())
+                    .unwrap_or_default(),
+                personality: personality.clone(),
+                motives: ALL_MOTIVES
+                    .iter()
+                    .map(|&motive| (motive, motives.get(motive)))
+                    .collect(),
+                position: world_position.0,
+                walk_target: walk_target.clone(),
+                // Actions on objects that are gone would be dropped on the next tick anyway
+                actions: queue
+                    .iter()
+                    .filter_map(|queued| {
+                        Some(SavedAction {
+                            object: *object_indices.get(&queued.action.object())?,
+                            action: queued.action.object_action().name.clone(),
+                            state: queued.state.clone(),
+                            source: queued.source,
+                        })
+                    })
+                    .collect(),
+                passed_out,
+                starving: starving.cloned(),
+            },
+        )
+        .collect();
+
+    let puddles = world
+        .query_filtered::<&WorldPosition, With<Puddle>>()
+        .iter(world)
+        .map(|world_position| world_position.0)
+        .collect();
+
+    SaveFile {
+        version: SAVE_VERSION,
+        time: world.resource::<SimulationTime>().clone(),
+        objects: saved_objects,
+        fellas: saved_fellas,
+        puddles,
+    }
+}
+
+fn resolve_action(
+    definition: &Arc<ObjectDefinition>,
+    object: Entity,
+    action: &str,
+) -> Result<FellaAction, LoadError> {
+    let index = definition
+        .actions
+        .iter()
+        .position(|object_action| object_action.name == action)
+        .ok_or_else(|| LoadError::UnknownAction {
+            object: definition.name.clone(),
+            action: action.to_string(),
+        })?;
+
+    Ok(FellaAction::UseObject(object, definition.clone(), index))
+}
+
+// Replaces every fella, object and puddle in the world with the ones in the save
+pub fn load_world(
+    world: &mut World,
+    save: &SaveFile,
+    definitions: &HashMap<String, Arc<ObjectDefinition>>,
+) -> Result<(), LoadError> {
+    if save.version != SAVE_VERSION {
+        return Err(LoadError::UnsupportedVersion(save.version));
+    }
+
+    // Check everything can be found before touching the world
+    let object_definitions = save
+        .objects
+        .iter()
+        .map(|object| {
+            definitions
+                .get(&object.definition)
+                .cloned()
+                .ok_or_else(|| LoadError::UnknownObject(object.definition.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for fella in save.fellas.iter() {
+        for action in fella.actions.iter() {
+            let definition = object_definitions
+                .get(action.object)
+                .ok_or_else(|| LoadError::UnknownObject(format!("#{}", action.object)))?;
+            resolve_action(definition, Entity::PLACEHOLDER, &action.action)?;
+
+            // The catalog might have fewer slots than when the game was saved
+            if let Some(slot) = action
+                .state
+                .slot()
+                .filter(|&slot| slot >= definition.capacity())
+            {
+                return Err(LoadError::UnknownSlot {
+                    object: definition.name.clone(),
+                    slot,
+                });
+            }
+        }
+    }
+
+    let existing: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(With<Fella>, With<InteractableObject>, With<Puddle>)>>()
+        .iter(world)
+        .collect();
+
+    for entity in existing {
+        world.entity_mut(entity).despawn_recursive();
+    }
+
+    let asset_server = world.resource::<AssetServer>().clone();
+    let sim_rng = world.resource::<SimRng>().clone();
+
+    let objects: Vec<Entity> = save
+        .objects
+        .iter()
+        .zip(object_definitions.iter())
+        .map(|(object, definition)| {
+            world
+                .spawn(object_bundle(
+                    definition.clone(),
+                    object.position,
+                    object.rotation,
+                    &asset_server,
+                ))
+                .id()
+        })
+        .collect();
+
+    // Fellas saved without an id get one nobody in the save has, so no two share a stream
+    let mut next_id = save
+        .fellas
+        .iter()
+        .filter_map(|fella| fella.id)
+        .map(|id| id.0 + 1)
+        .max()
+        .unwrap_or(0);
+
+    for fella in save.fellas.iter() {
+        let queue = fella
+            .actions
+            .iter()
+            .map(|action| {
+                let definition = &object_definitions[action.object];
+                let fella_action =
+                    resolve_action(definition, objects[action.object], &action.action)?;
+
+                let mut queued = QueuedAction::new(fella_action, action.source);
+                queued.state = action.state.clone();
+                Ok(queued)
+            })
+            .collect::<Result<ActionQueue, LoadError>>()?;
+
+        let mut entity = world.spawn(fella_bundle(
+            fella.id.unwrap_or_else(|| {
+                next_id += 1;
+                FellaId(next_id - 1)
+            }),
+            fella.name.clone(),
+            fella.personality.clone(),
+            fella.sprite.clone(),
+            fella.position,
+            &sim_rng,
+            &asset_server,
+        ));
+
+        let mut motives = BasicMotives::default();
+        for &(motive, value) in fella.motives.iter() {
+            motives.set(motive, value);
+        }
+
+        entity.insert((motives, queue, fella.walk_target.clone()));
+
+        if fella.passed_out {
+            entity.insert(PassedOut);
+        }
+
+        if let Some(starving) = fella.starving.clone() {
+            entity.insert(starving);
+        }
+    }
+
+    for &puddle in save.puddles.iter() {
+        world.spawn(puddle_bundle(puddle));
+    }
+
+    world.insert_resource(save.time.clone());
+
+    // Catalogs being reloaded would otherwise put their objects down again on top of the save's
+    world.insert_resource(PlaceCatalogObjects(false));
+
+    // Nothing from before the load refers to anything that still exists
+    world.insert_resource(SelectedFella(None));
+    world.insert_resource(EditHistory::default());
+
+    Ok(())
+}
+
+pub fn write_save_file(path: &Path, save: &SaveFile) -> Result<(), SaveError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let contents = ron::ser::to_string_pretty(save, ron::ser::PrettyConfig::default())?;
+    std::fs::write(path, contents)?;
+
+    Ok(())
+}
+
+// Reads a save file of any supported version, upgrading it to the current one
+pub fn read_save_file(path: &Path) -> Result<SaveFile, LoadError> {
+    let contents = std::fs::read_to_string(path)?;
+    migrations::parse_save(&contents)
+}
+
+// Every object definition in the loaded catalogs, by name
+fn catalog_definitions(world: &World) -> HashMap<String, Arc<ObjectDefinition>> {
+    world
+        .resource::<Assets<ObjectCatalog>>()
+        .iter()
+        .flat_map(|(_, catalog)| catalog.objects.iter())
+        .map(|definition| (definition.name.clone(), definition.clone()))
+        .collect()
+}
+
+pub struct SaveGame(pub PathBuf);
+
+impl Command for SaveGame {
+    fn apply(self, world: &mut World) {
+        let save = save_world(world);
+
+        match write_save_file(&self.0, &save) {
+            Ok(()) => println!("Saved to {}", self.0.display()),
+            Err(error) => println!("{}", error),
+        }
+    }
+}
+
+pub struct LoadGame(pub PathBuf);
+
+impl Command for LoadGame {
+    fn apply(self, world: &mut World) {
+        let definitions = catalog_definitions(world);
+        let result =
+            read_save_file(&self.0).and_then(|save| load_world(world, &save, &definitions));
+
+        match result {
+            Ok(()) => println!("Loaded {}", self.0.display()),
+            Err(error) => println!("{}", error),
+        }
+    }
+}
+
+// F5 to quicksave, F9 to quickload
+fn handle_save_keys(mut commands: Commands, keys: Res<Input<KeyCode>>) {
+    if keys.just_pressed(KeyCode::F5) {
+        commands.add(SaveGame(PathBuf::from(QUICKSAVE_PATH)));
+    }
+
+    if keys.just_pressed(KeyCode::F9) {
+        commands.add(LoadGame(PathBuf::from(QUICKSAVE_PATH)));
+    }
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, handle_save_keys)
+            .add_systems(SimulationTick, autosave_at_midnight.after(advance_time));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_utils::{action, definition, world_with_assets},
+        utility::ObjectAction,
+    };
+
+    use super::*;
+
+    fn definitions() -> HashMap<String, Arc<ObjectDefinition>> {
+        let fridge = Arc::new(ObjectDefinition {
+            sprite: String::from("gfx/fridge.aseprite"),
+            actions: vec![ObjectAction {
+                motive_changes: vec![(BasicMotive::Hunger, 0.5)],
+                duration: 30,
+                ..action("Eat")
+            }],
+            interaction_slots: vec![Vec2::new(0.0, -1.0)],
+            ..definition("Fridge")
+        });
+
+        HashMap::from([(fridge.name.clone(), fridge)])
+    }
+
+    // A fella on their way to the fridge, next to a puddle
+    fn populate(world: &mut World) {
+        let asset_server = world.resource::<AssetServer>().clone();
+        let fridge = definitions()["Fridge"].clone();
+
+        world.spawn(object_bundle(
+            fridge.clone(),
+            Vec2::new(3.0, 0.0),
+            Rotation::Quarter,
+            &asset_server,
+        ));
+        let other_fridge = world
+            .spawn(object_bundle(
+                fridge.clone(),
+                Vec2::new(5.0, 2.0),
+                Rotation::None,
+                &asset_server,
+            ))
+            .id();
+
+        let sim_rng = world.resource::<SimRng>().clone();
+        let mut fella = world.spawn(fella_bundle(
+            FellaId(0),
+            "Felix Fella",
+            Personality {
+                neat: 0.5,
+                ..default()
+            },
+            "gfx/fella01.aseprite",
+            Vec2::new(1.0, 1.0),
+            &sim_rng,
+            &asset_server,
+        ));
+
+        let mut motives = BasicMotives::default();
+        motives.set(BasicMotive::Hunger, 0.2);
+
+        let mut queued = QueuedAction::new(
+            FellaAction::UseObject(other_fridge, fridge, 0),
+            ActionSource::Player,
+        );
+        queued.state = ActionState::WalkingTo { slot: 0 };
+
+        fella.insert((
+            motives,
+            ActionQueue::from_iter([queued]),
+            WalkTarget {
+                target: Vec2::new(5.0, 1.0),
+                assigned_at: SimulationTime::default(),
+            },
+            PassedOut,
+        ));
+
+        world.spawn(puddle_bundle(Vec2::new(-1.0, 0.0)));
+    }
+
+    #[test]
+    fn saves_survive_a_round_trip() {
+        let mut world = world_with_assets();
+        populate(&mut world);
+        let saved = save_world(&mut world);
+
+        let contents = ron::to_string(&saved).unwrap();
+        let parsed: SaveFile = ron::from_str(&contents).unwrap();
+        assert_eq!(parsed, saved);
+
+        let mut loaded_world = world_with_assets();
+        load_world(&mut loaded_world, &parsed, &definitions()).unwrap();
+
+        assert_eq!(save_world(&mut loaded_world), saved);
+    }
+
+    #[test]
+    fn actions_point_at_the_loaded_objects() {
+        let mut world = world_with_assets();
+        populate(&mut world);
+        let saved = save_world(&mut world);
+
+        // Loading over the same world replaces everything that was there
+        load_world(&mut world, &saved, &definitions()).unwrap();
+
+        let queue = world.query::<&ActionQueue>().single(&world);
+        let object = queue.current().unwrap().action.object();
+        let position = world.get::<WorldPosition>(object).unwrap();
+
+        assert_eq!(position.0, Vec2::new(5.0, 2.0));
+        assert_eq!(world.query::<&InteractableObject>().iter(&world).count(), 2);
+    }
+
+    #[test]
+    fn unknown_objects_leave_the_world_alone() {
+        let mut world = world_with_assets();
+        populate(&mut world);
+        let mut saved = save_world(&mut world);
+        saved.objects[0].definition = String::from("Jacuzzi");
+
+        let result = load_world(&mut world, &saved, &definitions());
+
+        assert!(matches!(result, Err(LoadError::UnknownObject(_))));
+        assert_eq!(world.query::<&Fella>().iter(&world).count(), 1);
+    }
+
+    #[test]
+    fn slots_the_object_does_not_have_are_refused() {
+        let mut world = world_with_assets();
+        populate(&mut world);
+        let mut saved = save_world(&mut world);
+        saved.fellas[0].actions[0].state = ActionState::WalkingTo { slot: 1 };
+
+        let result = load_world(&mut world, &saved, &definitions());
+
+        assert!(matches!(
+            result,
+            Err(LoadError::UnknownSlot { slot: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn loading_stops_catalogs_placing_objects() {
+        let mut world = world_with_assets();
+        populate(&mut world);
+        let saved = save_world(&mut world);
+
+        load_world(&mut world, &saved, &definitions()).unwrap();
+
+        assert!(!world.resource::<PlaceCatalogObjects>().0);
+    }
+
+    #[test]
+    fn fellas_without_ids_get_unused_ones() {
+        let mut world = world_with_assets();
+        populate(&mut world);
+        let mut saved = save_world(&mut world);
+
+        let mut newcomer = saved.fellas[0].clone();
+        newcomer.id = None;
+        saved.fellas.insert(0, newcomer);
+
+        load_world(&mut world, &saved, &definitions()).unwrap();
+
+        let mut ids: Vec<FellaId> = world.query::<&FellaId>().iter(&world).copied().collect();
+        ids.sort_by_key(|id| id.0);
+
+        assert_eq!(ids, vec![FellaId(0), FellaId(1)]);
+    }
+
+    #[test]
+    fn newer_saves_are_refused() {
+        let mut world = world_with_assets();
+        let mut saved = save_world(&mut world);
+        saved.version = SAVE_VERSION + 1;
+
+        let result = load_world(&mut world, &saved, &definitions());
+
+        assert!(matches!(result, Err(LoadError::UnsupportedVersion(_))));
+    }
+}
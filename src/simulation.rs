@@ -0,0 +1,95 @@
+// Everything that makes the world tick, without anything to look at it or poke it with
+
+use bevy::prelude::*;
+
+use crate::{
+    action::ActionPlugin,
+    calendar::Calendar,
+    catalog::ObjectCatalogPlugin,
+    environment::EnvironmentPlugin,
+    failure::MotiveFailurePlugin,
+    fella::{create_fella, FellaId, FellaPlugin},
+    pathfinding::PathfindingPlugin,
+    personality::Personality,
+    rng::SimRng,
+    room::RoomPlugin,
+    time::{
+        advance_time, run_simulation_ticks, SimulationClock, SimulationTick, SimulationTime,
+        SpeedSettings, TimeScale,
+    },
+    world::WorldPlugin,
+};
+
+mod sprites {
+    use bevy_aseprite::aseprite;
+
+    aseprite!(pub Fella01, "gfx/fella01.aseprite");
+    aseprite!(pub Fella02, "gfx/fella02.aseprite");
+}
+
+fn spawn_fellas(mut commands: Commands, sim_rng: Res<SimRng>, asset_server: Res<AssetServer>) {
+    create_fella(
+        &mut commands,
+        FellaId(0),
+        "Felix Fella",
+        Personality {
+            neat: 0.8,
+            outgoing: -0.4,
+            active: -0.6,
+            playful: 0.6,
+            nice: 0.4,
+        },
+        sprites::Fella01::PATH,
+        Vec2::new(0.0, 0.0),
+        &sim_rng,
+        asset_server.as_ref(),
+    );
+    create_fella(
+        &mut commands,
+        FellaId(1),
+        "Fiona Fella",
+        Personality {
+            neat: -0.6,
+            outgoing: 0.8,
+            active: 0.7,
+            playful: -0.4,
+            nice: -0.2,
+        },
+        sprites::Fella02::PATH,
+        Vec2::new(1.0, 0.0),
+        &sim_rng,
+        asset_server.as_ref(),
+    );
+}
+
+pub struct SimulationPlugin {
+    // Same seed, same simulation
+    pub seed: u64,
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        println!("Simulation seed: {}", self.seed);
+
+        app.add_plugins((
+            FellaPlugin,
+            ActionPlugin,
+            MotiveFailurePlugin,
+            ObjectCatalogPlugin,
+            PathfindingPlugin,
+            RoomPlugin,
+            EnvironmentPlugin,
+            WorldPlugin,
+        ))
+        .insert_resource(SimulationTime::default())
+        .insert_resource(TimeScale::Normal)
+        .init_resource::<SimulationClock>()
+        .init_resource::<SpeedSettings>()
+        .init_resource::<Calendar>()
+        .insert_resource(SimRng::new(self.seed))
+        .add_systems(Startup, spawn_fellas)
+        .init_schedule(SimulationTick)
+        .add_systems(SimulationTick, advance_time)
+        .add_systems(Update, run_simulation_ticks);
+    }
+}
@@ -0,0 +1,45 @@
+// Fixtures shared by tests in different modules
+
+use bevy::prelude::*;
+use bevy_aseprite::Aseprite;
+
+use crate::{
+    rng::SimRng,
+    time::SimulationTime,
+    utility::{ObjectAction, ObjectDefinition},
+};
+
+// Enough of an app to spawn fellas and objects, whose sprites need the asset server
+pub fn world_with_assets() -> World {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<Aseprite>();
+    let mut world = std::mem::take(&mut app.world);
+    world.insert_resource(SimulationTime::default());
+    world.insert_resource(SimRng::new(0));
+    world
+}
+
+// A one tile object with no sprite, no actions and a slot on the object itself.
+// Change whatever matters to the test with `..definition(name)`.
+pub fn definition(name: &str) -> ObjectDefinition {
+    ObjectDefinition {
+        name: String::from(name),
+        sprite: String::new(),
+        actions: Vec::new(),
+        footprint: UVec2::ONE,
+        interaction_slots: vec![Vec2::ZERO],
+        environment: 0.0,
+        light: 0.0,
+    }
+}
+
+// Ten ticks of doing nothing for any motive
+pub fn action(name: &str) -> ObjectAction {
+    ObjectAction {
+        name: String::from(name),
+        motive_changes: Vec::new(),
+        duration: 10,
+        preconditions: Vec::new(),
+    }
+}
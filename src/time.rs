@@ -1,9 +1,26 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
 use bevy::{
-    ecs::system::{Res, ResMut, Resource},
-    time::Time,
+    ecs::{
+        entity::Entity,
+        schedule::ScheduleLabel,
+        system::{ResMut, Resource},
+        world::World,
+    },
+    time::{Real, Time},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    calendar::{Calendar, MINUTES_PER_HOUR},
+    fella::BasicMotive,
+    mood::critical_motives,
 };
 
-#[derive(Resource, Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Resource, Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct SimulationTime(f64);
 
 #[derive(Resource, Clone, Copy, Debug, Eq, PartialEq)]
@@ -12,12 +29,19 @@ pub enum TimeScale {
     Normal,
     Fast,
     Fastest,
+    // As fast as the computer can go
+    Ultra,
+    // As fast as the computer can go, then back to normal once it's time
+    Skip(SkipUntil),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SkipUntil {
+    Morning,
+    // A fella's motive has just become critical
+    NeedsAttention,
 }
 
-const MINUTES_PER_HOUR: u64 = 60;
-const HOURS_PER_DAY: u64 = 24;
-const TICKS_PER_DAY: u64 = MINUTES_PER_HOUR * HOURS_PER_DAY;
-const DAYS_PER_YEAR: u64 = 360;
 const DEFAULT_TICK: u64 = 720;
 
 impl Default for SimulationTime {
@@ -27,36 +51,46 @@ impl Default for SimulationTime {
 }
 
 impl SimulationTime {
-    // Converts from fractional seconds to ticks
+    // Older saves can be part way through a tick
     pub fn ticks(&self) -> u64 {
         self.0.floor() as u64
     }
 
+    pub fn from_ticks(ticks: u64) -> Self {
+        SimulationTime(ticks as f64)
+    }
+
     pub fn is_first_tick(&self) -> bool {
         self.ticks() == 0
     }
 
-    pub fn get_year(&self) -> u64 {
-        self.ticks() / TICKS_PER_DAY / DAYS_PER_YEAR
+    // Whole days since the clock started
+    pub fn days(&self, calendar: &Calendar) -> u64 {
+        self.ticks() / calendar.ticks_per_day()
+    }
+
+    pub fn get_year(&self, calendar: &Calendar) -> u64 {
+        self.days(calendar) / calendar.days_per_year()
     }
 
-    pub fn get_day(&self) -> u64 {
-        (self.ticks() / TICKS_PER_DAY) % DAYS_PER_YEAR
+    // Day of the year, counting from 0
+    pub fn get_day(&self, calendar: &Calendar) -> u64 {
+        self.days(calendar) % calendar.days_per_year()
     }
 
-    pub fn get_time(&self) -> u64 {
-        self.ticks() % TICKS_PER_DAY
+    pub fn get_time(&self, calendar: &Calendar) -> u64 {
+        self.ticks() % calendar.ticks_per_day()
     }
 
-    pub fn get_hour_minute(&self) -> (u32, u32) {
-        let time = self.get_time();
+    pub fn get_hour_minute(&self, calendar: &Calendar) -> (u32, u32) {
+        let time = self.get_time(calendar);
         let hour = time / MINUTES_PER_HOUR;
         let minute = time % MINUTES_PER_HOUR;
         (hour as u32, minute as u32)
     }
 
-    pub fn update(&mut self, delta_seconds: f64) {
-        self.0 += delta_seconds;
+    pub fn tick(&mut self) {
+        self.0 += 1.0;
     }
 
     pub fn time_since_ticks(&self, other: &SimulationTime) -> u64 {
@@ -66,49 +100,206 @@ impl SimulationTime {
     }
 }
 
-impl std::fmt::Display for SimulationTime {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let (hour, minute) = self.get_hour_minute();
-        write!(
-            f,
-            "Year {}, Day {}, {:02}:{:02}",
-            self.get_year(),
-            self.get_day(),
-            hour,
-            minute
-        )
+// Everything that changes the world runs once per tick, however fast the clock is going
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SimulationTick;
+
+#[derive(Resource, Clone, Debug)]
+pub struct SpeedSettings {
+    // Ticks per real second at each of the speeds that keep time
+    pub normal: f64,
+    pub fast: f64,
+    pub fastest: f64,
+    // Most ticks a single frame will run at those speeds. Ticks that didn't fit are
+    // caught up on over the next frames.
+    pub max_ticks_per_frame: u32,
+    // Most ticks that can be owed at once. If the simulation can't keep up, it falls behind
+    // instead of taking ever longer frames trying to catch up.
+    pub max_ticks_behind: u32,
+    // At ultra speed and while skipping, ticks run back to back for this long before
+    // the next frame gets drawn, but no more than the limit
+    pub flat_out_frame_time: Duration,
+    pub max_flat_out_ticks_per_frame: u32,
+}
+
+impl Default for SpeedSettings {
+    fn default() -> Self {
+        SpeedSettings {
+            normal: 1.0,
+            fast: 2.0,
+            fastest: 4.0,
+            max_ticks_per_frame: 64,
+            max_ticks_behind: 256,
+            flat_out_frame_time: Duration::from_millis(100),
+            max_flat_out_ticks_per_frame: 1000,
+        }
     }
 }
 
-#[derive(Resource, Clone, Debug)]
-pub struct SimulationDeltaTime(pub Option<f64>);
-
-impl SimulationDeltaTime {
-    fn update(&mut self, time_scale: &TimeScale, real_time: &Time) {
-        self.0 = match time_scale {
-            TimeScale::Paused => None,
-            TimeScale::Normal => Some(real_time.delta_seconds_f64()),
-            TimeScale::Fast => Some(real_time.delta_seconds_f64() * 2.0),
-            TimeScale::Fastest => Some(real_time.delta_seconds_f64() * 4.0),
+impl TimeScale {
+    // None when ticks run flat out rather than keeping time
+    pub fn ticks_per_second(&self, settings: &SpeedSettings) -> Option<f64> {
+        match self {
+            TimeScale::Paused => Some(0.0),
+            TimeScale::Normal => Some(settings.normal),
+            TimeScale::Fast => Some(settings.fast),
+            TimeScale::Fastest => Some(settings.fastest),
+            TimeScale::Ultra | TimeScale::Skip(_) => None,
+        }
+    }
+}
+
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SimulationClock {
+    // Ticks that are due but haven't run yet. Below one, it's how far into the next tick we are.
+    pending: f64,
+}
+
+impl SimulationClock {
+    // Lets real time pass and returns how many ticks to run for it, at most
+    pub fn advance(
+        &mut self,
+        real_delta_seconds: f64,
+        time_scale: &TimeScale,
+        settings: &SpeedSettings,
+    ) -> u32 {
+        let Some(ticks_per_second) = time_scale.ticks_per_second(settings) else {
+            self.pending = 0.0;
+            return settings.max_flat_out_ticks_per_frame;
         };
+
+        self.pending = (self.pending + real_delta_seconds * ticks_per_second)
+            .min(settings.max_ticks_behind as f64);
+
+        let ticks = self
+            .pending
+            .floor()
+            .min(settings.max_ticks_per_frame as f64);
+        self.pending -= ticks;
+
+        ticks as u32
+    }
+
+    // How far between the last tick and the next one we are, from 0 to 1
+    pub fn overstep(&self) -> f32 {
+        self.pending.min(1.0) as f32
+    }
+}
+
+// Motives that were critical before the last tick, for telling when another one becomes critical
+type CriticalMotives = HashSet<(Entity, BasicMotive)>;
+
+fn skip_is_over(world: &mut World, until: SkipUntil, critical: &mut CriticalMotives) -> bool {
+    match until {
+        SkipUntil::Morning => {
+            let calendar = world.resource::<Calendar>();
+            world.resource::<SimulationTime>().get_time(calendar) == calendar.morning()
+        }
+        SkipUntil::NeedsAttention => {
+            let now_critical = critical_motives(world);
+            let is_over = !now_critical.is_subset(critical);
+            *critical = now_critical;
+            is_over
+        }
+    }
+}
+
+pub fn run_simulation_ticks(world: &mut World) {
+    let real_delta_seconds = world.resource::<Time<Real>>().delta_seconds_f64();
+    let time_scale = *world.resource::<TimeScale>();
+    let settings = world.resource::<SpeedSettings>().clone();
+    let ticks =
+        world
+            .resource_mut::<SimulationClock>()
+            .advance(real_delta_seconds, &time_scale, &settings);
+
+    let is_flat_out = time_scale.ticks_per_second(&settings).is_none();
+    let started = Instant::now();
+    let mut critical = critical_motives(world);
+
+    for _ in 0..ticks {
+        world.run_schedule(SimulationTick);
+
+        if let TimeScale::Skip(until) = time_scale {
+            if skip_is_over(world, until, &mut critical) {
+                *world.resource_mut::<TimeScale>() = TimeScale::Normal;
+                break;
+            }
+        }
+
+        if is_flat_out && started.elapsed() >= settings.flat_out_frame_time {
+            break;
+        }
     }
 }
 
-pub fn update_simulation_time(
-    time_scale: Res<TimeScale>,
-    time: Res<Time>,
-    mut simulation_delta_time: ResMut<SimulationDeltaTime>,
-) {
-    simulation_delta_time.update(&time_scale, &time);
+pub fn advance_time(mut world_time: ResMut<SimulationTime>) {
+    world_time.tick();
 }
 
-pub fn advance_time(
-    simulation_time: Res<SimulationDeltaTime>,
-    mut world_time: ResMut<SimulationTime>,
-) {
-    let Some(simulation_delta_time) = simulation_time.0 else {
-        return;
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faster_speeds_run_more_ticks() {
+        let settings = SpeedSettings::default();
+        let mut clock = SimulationClock::default();
+
+        assert_eq!(clock.advance(1.0, &TimeScale::Paused, &settings), 0);
+        assert_eq!(clock.advance(1.0, &TimeScale::Normal, &settings), 1);
+        assert_eq!(clock.advance(1.0, &TimeScale::Fast, &settings), 2);
+        assert_eq!(clock.advance(1.0, &TimeScale::Fastest, &settings), 4);
+    }
+
+    #[test]
+    fn short_frames_add_up_to_a_tick() {
+        let settings = SpeedSettings::default();
+        let mut clock = SimulationClock::default();
 
-    world_time.update(simulation_delta_time);
+        assert_eq!(clock.advance(0.25, &TimeScale::Fast, &settings), 0);
+        assert_eq!(clock.overstep(), 0.5);
+        assert_eq!(clock.advance(0.25, &TimeScale::Fast, &settings), 1);
+        assert_eq!(clock.overstep(), 0.0);
+    }
+
+    #[test]
+    fn long_frames_are_capped() {
+        let settings = SpeedSettings::default();
+        let mut clock = SimulationClock::default();
+
+        let ticks_over_frames = |clock: &mut SimulationClock, real_delta_seconds| {
+            let mut ticks = vec![clock.advance(real_delta_seconds, &TimeScale::Fastest, &settings)];
+
+            while ticks.last() != Some(&0) {
+                ticks.push(clock.advance(0.0, &TimeScale::Fastest, &settings));
+            }
+
+            ticks
+        };
+
+        // A slow frame is made up for over the next ones
+        assert_eq!(ticks_over_frames(&mut clock, 25.0), vec![64, 36, 0]);
+
+        // But only so far behind, and never more than the cap in one frame
+        let ticks = ticks_over_frames(&mut clock, 3600.0);
+        assert_eq!(ticks.iter().sum::<u32>(), settings.max_ticks_behind);
+        assert!(ticks
+            .iter()
+            .all(|&ticks| ticks <= settings.max_ticks_per_frame));
+    }
+
+    #[test]
+    fn ultra_speed_runs_flat_out() {
+        let settings = SpeedSettings::default();
+        let mut clock = SimulationClock::default();
+
+        clock.advance(0.5, &TimeScale::Normal, &settings);
+
+        assert_eq!(
+            clock.advance(0.0, &TimeScale::Ultra, &settings),
+            settings.max_flat_out_ticks_per_frame
+        );
+        assert_eq!(clock.overstep(), 0.0);
+    }
 }
@@ -0,0 +1,157 @@
+// Shows what the selected fella is doing and is going to do, like the action queue in The Sims
+
+use bevy::prelude::*;
+
+use crate::{
+    action::{ActionQueue, CancelReason},
+    fella::SelectedFella,
+    time::SimulationTime,
+};
+
+use super::UI_FONT;
+
+#[derive(Component)]
+pub(super) struct ActionQueueStrip;
+
+// Clicking one cancels the action at that position in the queue
+#[derive(Component)]
+struct QueuedActionButton(usize);
+
+#[derive(Component)]
+struct QueuedActionProgress(usize);
+
+pub(super) fn create_action_queue_strip(parent: &mut ChildBuilder<'_, '_, '_>) {
+    parent.spawn((
+        ActionQueueStrip,
+        NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(8.0),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+fn rebuild_action_queue_strip(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    selected_fella: Res<SelectedFella>,
+    queues: Query<Ref<ActionQueue>>,
+    strip: Query<Entity, With<ActionQueueStrip>>,
+) {
+    let queue = selected_fella.0.and_then(|fella| queues.get(fella).ok());
+    let queue_changed = queue.as_ref().is_some_and(|queue| queue.is_changed());
+
+    if !selected_fella.is_changed() && !queue_changed {
+        return;
+    }
+
+    let strip = strip.single();
+    commands.entity(strip).despawn_descendants();
+
+    let Some(queue) = queue else {
+        return;
+    };
+
+    let font = asset_server.load::<Font>(UI_FONT);
+
+    commands.entity(strip).with_children(|parent| {
+        for (index, queued) in queue.iter().enumerate() {
+            parent
+                .spawn((
+                    QueuedActionButton(index),
+                    ButtonBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            padding: UiRect::all(Val::Px(4.0)),
+                            row_gap: Val::Px(2.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        queued.action.object_action().name.clone(),
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 16.0,
+                            color: Color::BLACK,
+                        },
+                    ));
+
+                    parent.spawn((
+                        QueuedActionProgress(index),
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Px(4.0),
+                                ..default()
+                            },
+                            background_color: BackgroundColor(Color::rgb(0.0, 0.7, 0.0)),
+                            ..default()
+                        },
+                    ));
+                });
+        }
+    });
+}
+
+fn update_queued_action_progress(
+    time: Res<SimulationTime>,
+    selected_fella: Res<SelectedFella>,
+    queues: Query<&ActionQueue>,
+    mut bars: Query<(&QueuedActionProgress, &mut Style)>,
+) {
+    let Some(queue) = selected_fella.0.and_then(|fella| queues.get(fella).ok()) else {
+        return;
+    };
+
+    for (bar, mut style) in bars.iter_mut() {
+        let progress = queue
+            .iter()
+            .nth(bar.0)
+            .map_or(0.0, |queued| queued.progress(&time));
+
+        style.width = Val::Percent(progress * 100.0);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn handle_queued_action_buttons(
+    selected_fella: Res<SelectedFella>,
+    mut queues: Query<&mut ActionQueue>,
+    buttons: Query<(&Interaction, &QueuedActionButton), (Changed<Interaction>, With<Button>)>,
+) {
+    let Some(fella) = selected_fella.0 else {
+        return;
+    };
+
+    let Ok(mut queue) = queues.get_mut(fella) else {
+        return;
+    };
+
+    for (interaction, button) in buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            queue.cancel(button.0, CancelReason::Cancelled);
+        }
+    }
+}
+
+pub(super) struct ActionQueueUiPlugin;
+
+impl Plugin for ActionQueueUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                handle_queued_action_buttons,
+                rebuild_action_queue_strip.after(handle_queued_action_buttons),
+                update_queued_action_progress.after(rebuild_action_queue_strip),
+            ),
+        );
+    }
+}
@@ -0,0 +1,437 @@
+// Build mode: buying objects from the catalog, and moving, turning and selling placed ones
+
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy_aseprite::AsepriteBundle;
+
+use crate::{
+    camera::MainCamera,
+    catalog::ObjectCatalog,
+    edit::{ApplyEdit, MoveFella, MoveObject, PlaceObject, RemoveObject},
+    fella::SelectedFella,
+    object::{check_placement, InteractableObject},
+    picking::{cursor_world_position, handle_on_click, OnPickEvent},
+    utility::ObjectDefinition,
+    world::{TilePosition, WorldGrid, WorldPosition},
+};
+
+use super::{UI_BLUE, UI_FONT};
+
+// Object following the cursor, waiting to be put down
+struct HeldObject {
+    object: InteractableObject,
+    // The placed object being moved, or None when buying a new one
+    moving: Option<Entity>,
+}
+
+#[derive(Resource, Default)]
+pub(super) struct BuildMode {
+    pub(super) active: bool,
+    held: Option<HeldObject>,
+}
+
+#[derive(Component)]
+struct BuildModeButton;
+
+#[derive(Component)]
+struct CatalogPanel;
+
+#[derive(Component)]
+struct CatalogButton(Arc<ObjectDefinition>);
+
+// Preview of where the held object would go
+#[derive(Component)]
+struct Ghost;
+
+pub(super) fn create_build_mode_button(parent: &mut ChildBuilder<'_, '_, '_>, font: &Handle<Font>) {
+    parent
+        .spawn((
+            BuildModeButton,
+            ButtonBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(4.0)),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Build",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 26.0,
+                    color: Color::BLACK,
+                },
+            ));
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn handle_build_mode_button(
+    mut build_mode: ResMut<BuildMode>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<BuildModeButton>)>,
+) {
+    for interaction in buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            build_mode.active = !build_mode.active;
+            build_mode.held = None;
+        }
+    }
+}
+
+fn update_catalog_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    build_mode: Res<BuildMode>,
+    catalogs: Res<Assets<ObjectCatalog>>,
+    mut catalog_events: EventReader<AssetEvent<ObjectCatalog>>,
+    panels: Query<Entity, With<CatalogPanel>>,
+) {
+    let catalogs_changed = catalog_events.read().count() > 0;
+
+    if !build_mode.is_changed() && !catalogs_changed {
+        return;
+    }
+
+    for panel in panels.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+
+    if !build_mode.active {
+        return;
+    }
+
+    let font = asset_server.load::<Font>(UI_FONT);
+
+    commands
+        .spawn((
+            CatalogPanel,
+            // Keeps clicks on the panel from reaching the world
+            Interaction::default(),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(0.0),
+                    top: Val::Px(0.0),
+                    min_width: Val::Px(160.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(16.0)),
+                    row_gap: Val::Px(8.0),
+                    ..default()
+                },
+                background_color: BackgroundColor(UI_BLUE),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Catalog",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            parent.spawn(TextBundle::from_section(
+                "R: turn, Del: sell, Esc: put back\nRight click: move selected fella",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 14.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            let definitions = catalogs
+                .iter()
+                .flat_map(|(_, catalog)| catalog.objects.iter());
+
+            for definition in definitions {
+                parent
+                    .spawn((
+                        CatalogButton(definition.clone()),
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(4.0)),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            definition.name.clone(),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 16.0,
+                                color: Color::BLACK,
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn handle_catalog_buttons(
+    mut build_mode: ResMut<BuildMode>,
+    buttons: Query<(&Interaction, &CatalogButton), (Changed<Interaction>, With<Button>)>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        build_mode.held = Some(HeldObject {
+            object: InteractableObject {
+                definition: button.0.clone(),
+                rotation: Default::default(),
+            },
+            moving: None,
+        });
+    }
+}
+
+// Tile under the cursor, where the held object would go
+fn cursor_tile(
+    window: &Query<&Window>,
+    camera: &Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) -> Option<TilePosition> {
+    let (camera, camera_transform) = camera.single();
+    let cursor_position = cursor_world_position(window.single(), camera, camera_transform)?;
+
+    Some(TilePosition::from_world(&WorldPosition::from_screen(
+        cursor_position,
+    )))
+}
+
+fn handle_fella_moves(
+    mut commands: Commands,
+    build_mode: Res<BuildMode>,
+    selected_fella: Res<SelectedFella>,
+    grid: Res<WorldGrid>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    window: Query<&Window>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    if !build_mode.active || !mouse_buttons.just_pressed(MouseButton::Right) {
+        return;
+    }
+
+    let (Some(fella), Some(tile)) = (selected_fella.0, cursor_tile(&window, &camera)) else {
+        return;
+    };
+
+    if !grid.is_walkable(tile) {
+        println!("Can't put a fella there");
+        return;
+    }
+
+    commands.add(ApplyEdit(Box::new(MoveFella::new(fella, tile.center().0))));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_build_clicks(
+    mut commands: Commands,
+    mut build_mode: ResMut<BuildMode>,
+    grid: Res<WorldGrid>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut pick_events: EventReader<OnPickEvent>,
+    window: Query<&Window>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    objects: Query<(Entity, &InteractableObject, &WorldPosition)>,
+    interactions: Query<&Interaction>,
+) {
+    let picked = pick_events
+        .read()
+        .find_map(|event| objects.get(event.0).ok());
+
+    if !build_mode.active {
+        return;
+    }
+
+    // Clicks on buttons and panels are for them, not the world
+    let over_ui = interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+
+    if over_ui || !mouse_buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Some(held) = build_mode.held.as_ref() else {
+        // Nothing in hand, so pick up whatever was clicked
+        if let Some((entity, object, _)) = picked {
+            build_mode.held = Some(HeldObject {
+                object: object.clone(),
+                moving: Some(entity),
+            });
+        }
+
+        return;
+    };
+
+    let Some(tile) = cursor_tile(&window, &camera) else {
+        return;
+    };
+
+    let position = tile.center();
+
+    if let Err(error) = check_placement(&grid, objects.iter(), &held.object, &position, held.moving)
+    {
+        println!("Can't put {} there: {}", held.object.definition.name, error);
+        return;
+    }
+
+    let rotation = held.object.rotation;
+
+    match held.moving {
+        Some(entity) => {
+            commands.add(ApplyEdit(Box::new(MoveObject::new(
+                entity, position.0, rotation,
+            ))));
+        }
+        None => {
+            let definition = held.object.definition.clone();
+            commands.add(ApplyEdit(Box::new(PlaceObject::new(
+                definition, position.0, rotation,
+            ))));
+        }
+    }
+
+    build_mode.held = None;
+}
+
+fn handle_build_keys(
+    mut commands: Commands,
+    mut build_mode: ResMut<BuildMode>,
+    keys: Res<Input<KeyCode>>,
+) {
+    if !build_mode.active {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        // Drop what's in hand first, and leave build mode after that
+        if build_mode.held.take().is_none() {
+            build_mode.active = false;
+        }
+
+        return;
+    }
+
+    let turn = keys.just_pressed(KeyCode::R);
+    let sell = keys.any_just_pressed([KeyCode::Delete, KeyCode::Back]);
+
+    // Only touch the held object when something happens, so the ghost isn't rebuilt every frame
+    if !turn && !sell {
+        return;
+    }
+
+    let Some(held) = build_mode.held.as_mut() else {
+        return;
+    };
+
+    if turn {
+        held.object.rotation = held.object.rotation.next();
+    }
+
+    if sell {
+        if let Some(entity) = held.moving {
+            commands.add(ApplyEdit(Box::new(RemoveObject::new(entity))));
+        }
+
+        build_mode.held = None;
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn update_ghost(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    build_mode: Res<BuildMode>,
+    grid: Res<WorldGrid>,
+    window: Query<&Window>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    objects: Query<(Entity, &InteractableObject, &WorldPosition)>,
+    mut ghosts: Query<
+        (
+            Entity,
+            &mut WorldPosition,
+            &mut Transform,
+            &mut TextureAtlasSprite,
+        ),
+        (With<Ghost>, Without<InteractableObject>),
+    >,
+) {
+    let held = build_mode.held.as_ref().filter(|_| build_mode.active);
+    let tile = cursor_tile(&window, &camera);
+
+    let (Some(held), Some(tile)) = (held, tile) else {
+        for (ghost, _, _, _) in ghosts.iter() {
+            commands.entity(ghost).despawn();
+        }
+
+        return;
+    };
+
+    // Picked something else up, so the ghost needs a different sprite, or the
+    // cursor just came back onto the grid and there's no ghost at all
+    if build_mode.is_changed() || ghosts.is_empty() {
+        for (ghost, _, _, _) in ghosts.iter() {
+            commands.entity(ghost).despawn();
+        }
+
+        commands.spawn((
+            Ghost,
+            AsepriteBundle {
+                aseprite: asset_server.load(held.object.definition.sprite.clone()),
+                transform: Transform::from_scale(Vec3::splat(2.0)),
+                ..default()
+            },
+            tile.center(),
+        ));
+
+        return;
+    }
+
+    let position = tile.center();
+    let is_valid =
+        check_placement(&grid, objects.iter(), &held.object, &position, held.moving).is_ok();
+
+    for (_, mut world_position, mut transform, mut sprite) in ghosts.iter_mut() {
+        *world_position = position.clone();
+        transform.rotation = Quat::from_rotation_z(held.object.rotation.radians());
+        sprite.color = if is_valid {
+            Color::rgba(1.0, 1.0, 1.0, 0.6)
+        } else {
+            Color::rgba(1.0, 0.2, 0.2, 0.6)
+        };
+    }
+}
+
+pub(super) struct BuildModeUiPlugin;
+
+impl Plugin for BuildModeUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BuildMode>().add_systems(
+            Update,
+            (
+                handle_build_mode_button,
+                update_catalog_panel.after(handle_build_mode_button),
+                handle_catalog_buttons,
+                handle_build_clicks
+                    .after(handle_catalog_buttons)
+                    .after(handle_on_click),
+                handle_build_keys,
+                handle_fella_moves,
+                update_ghost
+                    .after(handle_build_clicks)
+                    .after(handle_build_keys),
+            ),
+        );
+    }
+}
@@ -0,0 +1,187 @@
+// Right-click menu listing what the selected fella can do with an object
+
+use bevy::prelude::*;
+
+use crate::{
+    action::ActionQueue,
+    calendar::Calendar,
+    fella::{BasicMotives, SelectedFella},
+    object::InteractableObject,
+    picking::OnContextPickEvent,
+    time::SimulationTime,
+    utility::FellaAction,
+};
+
+use super::{build_mode::BuildMode, UI_BLUE, UI_FONT};
+
+#[derive(Component)]
+struct ContextMenu;
+
+#[derive(Component)]
+struct ContextMenuButton(FellaAction);
+
+#[allow(clippy::too_many_arguments)]
+fn open_context_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    time: Res<SimulationTime>,
+    calendar: Res<Calendar>,
+    build_mode: Res<BuildMode>,
+    selected_fella: Res<SelectedFella>,
+    mut context_pick_events: EventReader<OnContextPickEvent>,
+    objects: Query<&InteractableObject>,
+    fellas: Query<&BasicMotives>,
+    window: Query<&Window>,
+    menus: Query<Entity, With<ContextMenu>>,
+) {
+    let Some((object, interactable)) = context_pick_events
+        .read()
+        .find_map(|event| Some((event.0, objects.get(event.0).ok()?)))
+    else {
+        return;
+    };
+
+    // In build mode right-clicking moves the selected fella instead
+    if build_mode.active {
+        return;
+    }
+
+    for menu in menus.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+
+    let Some(motives) = selected_fella.0.and_then(|fella| fellas.get(fella).ok()) else {
+        println!("Select someone before telling them what to do");
+        return;
+    };
+
+    let Some(cursor_position) = window.single().cursor_position() else {
+        return;
+    };
+
+    let font = asset_server.load::<Font>(UI_FONT);
+    let definition = &interactable.definition;
+
+    commands
+        .spawn((
+            ContextMenu,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(cursor_position.x),
+                    top: Val::Px(cursor_position.y),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(8.0)),
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                background_color: BackgroundColor(UI_BLUE),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                definition.name.clone(),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            for (index, action) in definition.actions.iter().enumerate() {
+                if !action.is_available(motives, &time, &calendar) {
+                    continue;
+                }
+
+                parent
+                    .spawn((
+                        ContextMenuButton(FellaAction::UseObject(
+                            object,
+                            definition.clone(),
+                            index,
+                        )),
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(4.0)),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            action.name.clone(),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 16.0,
+                                color: Color::BLACK,
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn handle_context_menu_buttons(
+    mut commands: Commands,
+    selected_fella: Res<SelectedFella>,
+    mut queues: Query<&mut ActionQueue>,
+    buttons: Query<(&Interaction, &ContextMenuButton), (Changed<Interaction>, With<Button>)>,
+    menus: Query<Entity, With<ContextMenu>>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        if let Some(mut queue) = selected_fella
+            .0
+            .and_then(|fella| queues.get_mut(fella).ok())
+        {
+            queue.command(button.0.clone());
+        }
+
+        for menu in menus.iter() {
+            commands.entity(menu).despawn_recursive();
+        }
+    }
+}
+
+fn close_context_menu(
+    mut commands: Commands,
+    mouse_buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Query<&Interaction, With<ContextMenuButton>>,
+    menus: Query<Entity, With<ContextMenu>>,
+) {
+    let clicked_outside = mouse_buttons.just_pressed(MouseButton::Left)
+        && buttons
+            .iter()
+            .all(|interaction| *interaction == Interaction::None);
+
+    if !clicked_outside && !keys.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    for menu in menus.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+}
+
+pub(super) struct ContextMenuPlugin;
+
+impl Plugin for ContextMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                open_context_menu,
+                handle_context_menu_buttons,
+                close_context_menu,
+            ),
+        );
+    }
+}
@@ -0,0 +1,193 @@
+// Picking a save to load, listed with the in-game date it was made on
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use crate::{
+    calendar::Calendar,
+    save::{read_save_file, save_slots, LoadGame},
+};
+
+use super::{UI_BLUE, UI_FONT};
+
+#[derive(Resource, Default)]
+pub(super) struct LoadMenu {
+    open: bool,
+}
+
+#[derive(Component)]
+struct LoadMenuButton;
+
+#[derive(Component)]
+struct LoadPanel;
+
+#[derive(Component)]
+struct LoadSlotButton(PathBuf);
+
+pub(super) fn create_load_menu_button(parent: &mut ChildBuilder<'_, '_, '_>, font: &Handle<Font>) {
+    parent
+        .spawn((
+            LoadMenuButton,
+            ButtonBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(4.0)),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Load",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 26.0,
+                    color: Color::BLACK,
+                },
+            ));
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn handle_load_menu_button(
+    mut load_menu: ResMut<LoadMenu>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<LoadMenuButton>)>,
+) {
+    for interaction in buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            load_menu.open = !load_menu.open;
+        }
+    }
+}
+
+fn update_load_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    calendar: Res<Calendar>,
+    load_menu: Res<LoadMenu>,
+    panels: Query<Entity, With<LoadPanel>>,
+) {
+    if !load_menu.is_changed() {
+        return;
+    }
+
+    for panel in panels.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+
+    if !load_menu.open {
+        return;
+    }
+
+    let font = asset_server.load::<Font>(UI_FONT);
+
+    // Saves are read again every time the menu opens, so new autosaves show up
+    let saves: Vec<_> = save_slots()
+        .into_iter()
+        .filter_map(|(name, path)| {
+            let save = read_save_file(&path).ok()?;
+            Some((format!("{}: {}", name, calendar.format(&save.time)), path))
+        })
+        .collect();
+
+    commands
+        .spawn((
+            LoadPanel,
+            // Keeps clicks on the panel from reaching the world
+            Interaction::default(),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(0.0),
+                    bottom: Val::Px(50.0),
+                    min_width: Val::Px(240.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(16.0)),
+                    row_gap: Val::Px(8.0),
+                    ..default()
+                },
+                background_color: BackgroundColor(UI_BLUE),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Load",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            if saves.is_empty() {
+                parent.spawn(TextBundle::from_section(
+                    "No saves yet",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                    },
+                ));
+            }
+
+            for (label, path) in saves {
+                parent
+                    .spawn((
+                        LoadSlotButton(path),
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(4.0)),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 16.0,
+                                color: Color::BLACK,
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn handle_load_slot_buttons(
+    mut commands: Commands,
+    mut load_menu: ResMut<LoadMenu>,
+    buttons: Query<(&Interaction, &LoadSlotButton), (Changed<Interaction>, With<Button>)>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        commands.add(LoadGame(button.0.clone()));
+        load_menu.open = false;
+    }
+}
+
+pub(super) struct LoadMenuUiPlugin;
+
+impl Plugin for LoadMenuUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadMenu>().add_systems(
+            Update,
+            (
+                handle_load_menu_button,
+                handle_load_slot_buttons,
+                update_load_panel
+                    .after(handle_load_menu_button)
+                    .after(handle_load_slot_buttons),
+            ),
+        );
+    }
+}
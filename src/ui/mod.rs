@@ -1,8 +1,21 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+
+mod action_queue;
+mod build_mode;
+mod context_menu;
+mod load_menu;
+
+use action_queue::{create_action_queue_strip, ActionQueueUiPlugin};
+use build_mode::{create_build_mode_button, BuildModeUiPlugin};
+use context_menu::ContextMenuPlugin;
+use load_menu::{create_load_menu_button, LoadMenuUiPlugin};
 
 use crate::{
+    calendar::Calendar,
+    edit::{ApplyEdit, SetMotive},
     fella::{BasicMotive, BasicMotives, Fella, Named, SelectedFella, ALL_MOTIVES},
-    time::{SimulationTime, TimeScale},
+    mood::Mood,
+    time::{SimulationTime, SkipUntil, TimeScale},
 };
 
 #[derive(Component)]
@@ -12,10 +25,11 @@ struct TimeText;
 struct SpeedButton(TimeScale);
 
 const UI_BLUE: Color = Color::rgba(0.1, 0.1, 1.0, 0.8);
+const UI_FONT: &str = "fonts/ComicNeue-Bold.ttf";
 
 fn create_ui(asset_server: Res<'_, AssetServer>, mut commands: Commands<'_, '_>) {
     // Add bottom bar and time display
-    let font = asset_server.load::<Font>("fonts/ComicNeue-Bold.ttf");
+    let font = asset_server.load::<Font>(UI_FONT);
 
     // Root node
     commands
@@ -58,12 +72,13 @@ fn create_ui(asset_server: Res<'_, AssetServer>, mut commands: Commands<'_, '_>)
                             font: font.clone(),
                             font_size: 26.0,
                             color: Color::BLACK,
-                            ..default()
                         },
                     );
 
                     parent.spawn(bundle).insert(TimeText);
 
+                    create_action_queue_strip(parent);
+
                     parent
                         .spawn(NodeBundle {
                             style: Style {
@@ -75,10 +90,25 @@ fn create_ui(asset_server: Res<'_, AssetServer>, mut commands: Commands<'_, '_>)
                             ..default()
                         })
                         .with_children(|parent| {
+                            create_build_mode_button(parent, &font);
+                            create_load_menu_button(parent, &font);
                             create_speed_button(parent, &font, "||", TimeScale::Paused);
                             create_speed_button(parent, &font, "1x", TimeScale::Normal);
                             create_speed_button(parent, &font, "2x", TimeScale::Fast);
                             create_speed_button(parent, &font, "4x", TimeScale::Fastest);
+                            create_speed_button(parent, &font, "Ultra", TimeScale::Ultra);
+                            create_speed_button(
+                                parent,
+                                &font,
+                                "Morning",
+                                TimeScale::Skip(SkipUntil::Morning),
+                            );
+                            create_speed_button(
+                                parent,
+                                &font,
+                                "Until needed",
+                                TimeScale::Skip(SkipUntil::NeedsAttention),
+                            );
                         });
                 });
         });
@@ -109,7 +139,6 @@ fn create_speed_button(
                         font: font.clone(),
                         font_size: 26.0,
                         color: Color::BLACK,
-                        ..default()
                     },
                 ),
                 ..default()
@@ -120,9 +149,16 @@ fn create_speed_button(
 #[derive(Component)]
 struct MotiveBar(BasicMotive);
 
+// Clicking the bar sets the motive of the selected fella
+#[derive(Component)]
+struct MotiveBarContainer(BasicMotive);
+
 #[derive(Component)]
 struct SelectedFellaLabel;
 
+#[derive(Component)]
+struct MoodBar;
+
 fn create_motives_panel(parent: &mut ChildBuilder<'_, '_, '_>, font: &Handle<Font>) {
     parent
         .spawn(NodeBundle {
@@ -153,6 +189,8 @@ fn create_motives_panel(parent: &mut ChildBuilder<'_, '_, '_>, font: &Handle<Fon
                 },
             ));
 
+            create_mood_meter(parent, font);
+
             parent
                 .spawn(NodeBundle {
                     style: Style {
@@ -195,18 +233,23 @@ fn create_motives_panel(parent: &mut ChildBuilder<'_, '_, '_>, font: &Handle<Fon
 
                                 // Container
                                 parent
-                                    .spawn(NodeBundle {
-                                        style: Style {
-                                            width: Val::Percent(100.0),
-                                            height: Val::Px(16.0),
-                                            padding: UiRect::all(Val::Px(2.0)),
+                                    .spawn((
+                                        MotiveBarContainer(motive),
+                                        Interaction::default(),
+                                        RelativeCursorPosition::default(),
+                                        NodeBundle {
+                                            style: Style {
+                                                width: Val::Percent(100.0),
+                                                height: Val::Px(16.0),
+                                                padding: UiRect::all(Val::Px(2.0)),
+                                                ..default()
+                                            },
+                                            background_color: BackgroundColor(Color::rgb(
+                                                0.0, 0.0, 0.2,
+                                            )),
                                             ..default()
                                         },
-                                        background_color: BackgroundColor(Color::rgb(
-                                            0.0, 0.0, 0.2,
-                                        )),
-                                        ..default()
-                                    })
+                                    ))
                                     .with_children(|parent| {
                                         parent.spawn((
                                             MotiveBar(motive),
@@ -229,31 +272,76 @@ fn create_motives_panel(parent: &mut ChildBuilder<'_, '_, '_>, font: &Handle<Fon
         });
 }
 
+fn create_mood_meter(parent: &mut ChildBuilder<'_, '_, '_>, font: &Handle<Font>) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Mood",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(24.0),
+                        padding: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::rgb(0.0, 0.0, 0.2)),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        MoodBar,
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(50.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: BackgroundColor(Color::rgb(0.0, 0.7, 0.0)),
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
+
 fn update_time_display(
     world_time: Res<SimulationTime>,
+    calendar: Res<Calendar>,
     time_scale: Res<TimeScale>,
     mut query: Query<&mut Text, With<TimeText>>,
 ) {
     if world_time.is_changed() || time_scale.is_changed() || world_time.is_first_tick() {
         for mut text in query.iter_mut() {
-            text.sections[0].value = format!("{} {:?}", world_time.as_ref(), time_scale.as_ref());
+            text.sections[0].value =
+                format!("{} {:?}", calendar.format(&world_time), time_scale.as_ref());
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn handle_time_scale_button_events(
     mut time_scale: ResMut<TimeScale>,
-    mut query: Query<
-        (&Interaction, &mut BackgroundColor, &SpeedButton),
-        (Changed<Interaction>, With<Button>),
-    >,
+    query: Query<(&Interaction, &SpeedButton), (Changed<Interaction>, With<Button>)>,
 ) {
-    for (interaction, mut _background_color, speed_button) in query.iter_mut() {
-        match *interaction {
-            Interaction::Pressed => {
-                *time_scale = speed_button.0;
-            }
-            _ => {}
+    for (interaction, speed_button) in query.iter() {
+        if *interaction == Interaction::Pressed {
+            *time_scale = speed_button.0;
         }
     }
 }
@@ -296,20 +384,76 @@ fn update_motive_bars(
     }
 }
 
+#[allow(clippy::type_complexity)]
+fn handle_motive_bar_clicks(
+    mut commands: Commands,
+    selected_fella: Res<SelectedFella>,
+    containers: Query<
+        (&Interaction, &RelativeCursorPosition, &MotiveBarContainer),
+        Changed<Interaction>,
+    >,
+) {
+    let Some(selected_fella) = selected_fella.0 else {
+        return;
+    };
+
+    for (interaction, cursor_position, container) in containers.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let Some(cursor_position) = cursor_position.normalized else {
+            continue;
+        };
+
+        commands.add(ApplyEdit(Box::new(SetMotive::new(
+            selected_fella,
+            container.0,
+            cursor_position.x.clamp(0.0, 1.0),
+        ))));
+    }
+}
+
+fn update_mood_bar(
+    selected_fella: Res<SelectedFella>,
+    fellas: Query<&Mood, With<Fella>>,
+    mut query: Query<(&mut Style, &mut BackgroundColor), With<MoodBar>>,
+) {
+    let Some(mood) = selected_fella.0.and_then(|fella| fellas.get(fella).ok()) else {
+        return;
+    };
+
+    // Mood goes from -1.0 to 1.0, fading from red to green
+    let fraction = (mood.0 + 1.0) / 2.0;
+
+    for (mut style, mut background_color) in query.iter_mut() {
+        style.width = Val::Percent(fraction * 100.0);
+        background_color.0 = Color::rgb(0.8 * (1.0 - fraction), 0.7 * fraction, 0.0);
+    }
+}
+
 pub struct GameUiPlugin;
 
 impl Plugin for GameUiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, create_ui)
-            .add_systems(
-                Update,
-                (
-                    update_time_display,
-                    handle_time_scale_button_events,
-                    on_fella_selected,
-                    update_motive_bars,
-                ),
-            )
-            .insert_resource(SelectedFella(None));
+        app.add_plugins((
+            ActionQueueUiPlugin,
+            BuildModeUiPlugin,
+            ContextMenuPlugin,
+            LoadMenuUiPlugin,
+        ))
+        .add_systems(Startup, create_ui)
+        .add_systems(
+            Update,
+            (
+                update_time_display,
+                handle_time_scale_button_events,
+                on_fella_selected,
+                update_motive_bars,
+                handle_motive_bar_clicks,
+                update_mood_bar,
+            ),
+        )
+        .insert_resource(SelectedFella(None));
     }
 }
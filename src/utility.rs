@@ -1,24 +1,454 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
-use bevy::ecs::entity::Entity;
+use bevy::{
+    ecs::entity::Entity,
+    math::{UVec2, Vec2},
+};
+use rand::Rng;
+use serde::Deserialize;
 
-use crate::fella::{BasicMotive, BasicMotives};
+use crate::{
+    calendar::Calendar,
+    fella::{BasicMotive, BasicMotives},
+    mood::compute_mood,
+    personality::Personality,
+    time::SimulationTime,
+};
 
+#[derive(Clone, Debug)]
 pub enum FellaAction {
-    // TODO: Support multiple actions per object
-    UseObject(Entity, Rc<ObjectDefinition>),
+    // Object, its definition and the index of the action in the definition
+    UseObject(Entity, Arc<ObjectDefinition>, usize),
 }
 
-struct ObjectDefinition {
-    motive_changes: Vec<(BasicMotive, f32)>,
+impl FellaAction {
+    pub fn object(&self) -> Entity {
+        match self {
+            FellaAction::UseObject(object, _, _) => *object,
+        }
+    }
+
+    pub fn definition(&self) -> &ObjectDefinition {
+        match self {
+            FellaAction::UseObject(_, definition, _) => definition,
+        }
+    }
+
+    pub fn object_action(&self) -> &ObjectAction {
+        match self {
+            FellaAction::UseObject(_, definition, action) => &definition.actions[*action],
+        }
+    }
+}
+
+// Same action on the same object
+impl PartialEq for FellaAction {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (FellaAction::UseObject(a, _, a_action), FellaAction::UseObject(b, _, b_action)) => {
+                a == b && a_action == b_action
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for FellaAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({})",
+            self.object_action().name,
+            self.definition().name
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ObjectDefinition {
+    pub name: String,
+    // Path to the Aseprite file used to draw the object
+    pub sprite: String,
+    pub actions: Vec<ObjectAction>,
+    // Size of the object in tiles, extending right and up from its position
+    pub footprint: UVec2,
+    // Where fellas stand while using the object, relative to the object itself.
+    // There's one slot per fella that can use the object at the same time.
+    pub interaction_slots: Vec<Vec2>,
+    // How much nicer (or, when negative, worse) the object makes the room it's in
+    #[serde(default)]
+    pub environment: f32,
+    // How much light the object gives off, 1.0 lights up a room on its own
+    #[serde(default)]
+    pub light: f32,
+}
+
+impl ObjectDefinition {
+    pub fn capacity(&self) -> usize {
+        self.interaction_slots.len()
+    }
+}
+
+// Something a fella can do with an object, e.g. "Sleep" or "Nap" for a bed
+#[derive(Debug, Deserialize)]
+pub struct ObjectAction {
+    pub name: String,
+    // Total change to each motive over the whole action
+    pub motive_changes: Vec<(BasicMotive, f32)>,
+    // How many ticks the action takes
+    pub duration: u64,
+    // All of these must hold for the action to be advertised
+    #[serde(default)]
+    pub preconditions: Vec<Precondition>,
+}
+
+impl ObjectAction {
+    pub fn is_available(
+        &self,
+        motives: &BasicMotives,
+        time: &SimulationTime,
+        calendar: &Calendar,
+    ) -> bool {
+        self.preconditions
+            .iter()
+            .all(|precondition| precondition.holds(motives, time, calendar))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub enum Precondition {
+    MotiveBelow(BasicMotive, f32),
+    MotiveAbove(BasicMotive, f32),
+    // Start and end hour, wrapping around midnight if start > end
+    BetweenHours(u32, u32),
+    // Names of the weekdays, as the calendar has them
+    OnWeekdays(Vec<String>),
+}
+
+impl Precondition {
+    pub fn holds(
+        &self,
+        motives: &BasicMotives,
+        time: &SimulationTime,
+        calendar: &Calendar,
+    ) -> bool {
+        match *self {
+            Precondition::MotiveBelow(motive, value) => motives.get(motive) < value,
+            Precondition::MotiveAbove(motive, value) => motives.get(motive) > value,
+            Precondition::BetweenHours(start, end) => calendar.is_between_hours(time, start, end),
+            Precondition::OnWeekdays(ref weekdays) => calendar.is_on_weekdays(time, weekdays),
+        }
+    }
+}
+
+pub struct ScoreActionInput<'a> {
+    pub current_motives: &'a BasicMotives,
+    pub personality: &'a Personality,
+    pub action: &'a ObjectAction,
+    pub distance: f32,
+}
+
+// How much each world unit of walking reduces the appeal of an object
+const DISTANCE_ATTENUATION: f32 = 0.1;
+
+// How steeply the urgency of a motive grows as it empties.
+// Physical needs become desperate much faster than the rest.
+fn motive_steepness(motive: BasicMotive) -> f32 {
+    match motive {
+        BasicMotive::Hunger | BasicMotive::Bathroom | BasicMotive::Energy => 5.0,
+        BasicMotive::Hygiene => 3.5,
+        BasicMotive::Social | BasicMotive::Fun | BasicMotive::Comfort => 3.0,
+        BasicMotive::Environment => 2.0,
+    }
 }
 
-struct ScoreActionInput {
-    current_motives: BasicMotives,
-    object: Rc<ObjectDefinition>,
-    distance: f32,
+// Response curve: 0.0 for a full motive, growing exponentially as it approaches empty
+fn motive_urgency(motive: BasicMotive, value: f32) -> f32 {
+    let emptiness = 1.0 - value.clamp(0.0, 1.0);
+    (motive_steepness(motive) * emptiness).exp() - 1.0
 }
 
+// How much improving the mood is worth on top of the motives themselves
+const MOOD_SCORE_WEIGHT: f32 = 10.0;
+
 pub fn score_action(input: &ScoreActionInput) -> f32 {
-    0.0
+    let mut score = 0.0;
+    let mut motives_after = input.current_motives.clone();
+
+    for &(motive, change) in input.action.motive_changes.iter() {
+        let current = input.current_motives.get(motive);
+        let after = (current + change).clamp(0.0, 1.0);
+
+        // Positive when the action relieves urgency, negative when it makes things worse
+        let relief = motive_urgency(motive, current) - motive_urgency(motive, after);
+        score += relief * input.personality.motive_sensitivity(motive);
+
+        motives_after.change(motive, change);
+    }
+
+    // Grumpy fellas go for whatever cheers them up the most
+    let mood = compute_mood(input.current_motives);
+    let mood_improvement = compute_mood(&motives_after) - mood;
+    score += mood_improvement * MOOD_SCORE_WEIGHT * (1.0 - mood);
+
+    score / (1.0 + input.distance.max(0.0) * DISTANCE_ATTENUATION)
+}
+
+// How many of the best scoring actions are considered when picking one
+pub const TOP_ACTION_COUNT: usize = 3;
+
+// Actions scoring at or below this are not worth walking over for
+pub const MIN_ACTION_SCORE: f32 = 0.5;
+
+// Picks one of the top N candidates at random, weighted by score.
+// Always taking the single best action makes fellas look robotic, so like in The Sims
+// we leave some room for less optimal (but still reasonable) choices.
+pub fn pick_weighted_top_n<T>(
+    mut candidates: Vec<(T, f32)>,
+    top_n: usize,
+    min_score: f32,
+    rng: &mut impl Rng,
+) -> Option<T> {
+    candidates.retain(|(_, score)| score.is_finite() && *score > min_score);
+    candidates.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    candidates.truncate(top_n);
+
+    let total: f32 = candidates.iter().map(|(_, score)| score).sum();
+
+    if candidates.is_empty() || total <= 0.0 {
+        return None;
+    }
+
+    let mut roll = rng.gen_range(0.0..total);
+    let last_index = candidates.len() - 1;
+
+    for (index, (candidate, score)) in candidates.into_iter().enumerate() {
+        if roll < score || index == last_index {
+            return Some(candidate);
+        }
+
+        roll -= score;
+    }
+
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn action(name: &str, motive_changes: Vec<(BasicMotive, f32)>) -> ObjectAction {
+        ObjectAction {
+            name: String::from(name),
+            motive_changes,
+            duration: 10,
+            preconditions: Vec::new(),
+        }
+    }
+
+    fn hamburger() -> ObjectAction {
+        action("Eat", vec![(BasicMotive::Hunger, 0.5)])
+    }
+
+    fn bed() -> ObjectAction {
+        action(
+            "Sleep",
+            vec![(BasicMotive::Energy, 0.7), (BasicMotive::Comfort, 0.2)],
+        )
+    }
+
+    fn score(motives: &BasicMotives, action: ObjectAction, distance: f32) -> f32 {
+        score_with_personality(motives, &Personality::default(), action, distance)
+    }
+
+    fn score_with_personality(
+        motives: &BasicMotives,
+        personality: &Personality,
+        action: ObjectAction,
+        distance: f32,
+    ) -> f32 {
+        score_action(&ScoreActionInput {
+            current_motives: motives,
+            personality,
+            action: &action,
+            distance,
+        })
+    }
+
+    #[test]
+    fn starving_fella_prefers_hamburger_over_bed() {
+        let mut motives = BasicMotives::default();
+        motives.set(BasicMotive::Hunger, 0.05);
+
+        let hamburger_score = score(&motives, hamburger(), 4.0);
+        let bed_score = score(&motives, bed(), 4.0);
+
+        assert!(
+            hamburger_score > bed_score,
+            "hamburger {} should beat bed {}",
+            hamburger_score,
+            bed_score
+        );
+    }
+
+    #[test]
+    fn starving_fella_prefers_distant_hamburger_over_nearby_bed() {
+        let mut motives = BasicMotives::default();
+        motives.set(BasicMotive::Hunger, 0.05);
+
+        assert!(score(&motives, hamburger(), 10.0) > score(&motives, bed(), 1.0));
+    }
+
+    #[test]
+    fn exhausted_fella_prefers_bed_over_hamburger() {
+        let mut motives = BasicMotives::default();
+        motives.set(BasicMotive::Energy, 0.05);
+
+        assert!(score(&motives, bed(), 4.0) > score(&motives, hamburger(), 4.0));
+    }
+
+    #[test]
+    fn lower_motives_score_higher() {
+        let mut hungry = BasicMotives::default();
+        hungry.set(BasicMotive::Hunger, 0.2);
+
+        let mut peckish = BasicMotives::default();
+        peckish.set(BasicMotive::Hunger, 0.6);
+
+        assert!(score(&hungry, hamburger(), 0.0) > score(&peckish, hamburger(), 0.0));
+    }
+
+    #[test]
+    fn distance_attenuates_score() {
+        let mut motives = BasicMotives::default();
+        motives.set(BasicMotive::Hunger, 0.3);
+
+        let near = score(&motives, hamburger(), 1.0);
+        let far = score(&motives, hamburger(), 20.0);
+
+        assert!(near > far);
+        assert!(far > 0.0);
+    }
+
+    #[test]
+    fn full_motive_gives_no_score() {
+        let mut motives = BasicMotives::default();
+        motives.set(BasicMotive::Hunger, 1.0);
+
+        assert_eq!(score(&motives, hamburger(), 0.0), 0.0);
+    }
+
+    #[test]
+    fn harmful_changes_score_negative() {
+        let motives = BasicMotives::default();
+        let puddle = action("Splash in", vec![(BasicMotive::Hygiene, -0.3)]);
+
+        assert!(score(&motives, puddle, 0.0) < 0.0);
+    }
+
+    #[test]
+    fn neat_fella_prefers_showering_more_than_sloppy_fella() {
+        let mut motives = BasicMotives::default();
+        motives.set(BasicMotive::Hygiene, 0.3);
+        motives.set(BasicMotive::Fun, 0.3);
+
+        let neat = Personality {
+            neat: 1.0,
+            ..Personality::default()
+        };
+        let sloppy = Personality {
+            neat: -1.0,
+            playful: 1.0,
+            ..Personality::default()
+        };
+
+        let shower = || action("Shower", vec![(BasicMotive::Hygiene, 0.5)]);
+        let play = || action("Play", vec![(BasicMotive::Fun, 0.5)]);
+
+        assert!(
+            score_with_personality(&motives, &neat, shower(), 1.0)
+                > score_with_personality(&motives, &neat, play(), 1.0)
+        );
+        assert!(
+            score_with_personality(&motives, &sloppy, play(), 1.0)
+                > score_with_personality(&motives, &sloppy, shower(), 1.0)
+        );
+    }
+
+    #[test]
+    fn pick_ignores_candidates_below_threshold() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let candidates = vec![("bed", 0.1), ("hamburger", 0.2)];
+
+        assert_eq!(pick_weighted_top_n(candidates, 3, 0.5, &mut rng), None);
+    }
+
+    #[test]
+    fn pick_only_considers_top_n() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let candidates = vec![("toilet", 1.0), ("hamburger", 10.0), ("bed", 9.0)];
+            let picked = pick_weighted_top_n(candidates, 2, 0.0, &mut rng);
+
+            assert_ne!(picked, Some("toilet"));
+        }
+    }
+
+    #[test]
+    fn motive_preconditions() {
+        let mut nap = bed();
+        nap.preconditions = vec![Precondition::MotiveBelow(BasicMotive::Energy, 0.6)];
+
+        let time = SimulationTime::default();
+        let calendar = Calendar::default();
+        let mut motives = BasicMotives::default();
+
+        motives.set(BasicMotive::Energy, 0.8);
+        assert!(!nap.is_available(&motives, &time, &calendar));
+
+        motives.set(BasicMotive::Energy, 0.3);
+        assert!(nap.is_available(&motives, &time, &calendar));
+    }
+
+    #[test]
+    fn hour_preconditions_wrap_around_midnight() {
+        // The default time is noon
+        let time = SimulationTime::default();
+        let calendar = Calendar::default();
+        let motives = BasicMotives::default();
+
+        let mut sleep = bed();
+        sleep.preconditions = vec![Precondition::BetweenHours(22, 6)];
+        assert!(!sleep.is_available(&motives, &time, &calendar));
+
+        let mut work = bed();
+        work.preconditions = vec![Precondition::BetweenHours(9, 17)];
+        assert!(work.is_available(&motives, &time, &calendar));
+    }
+
+    #[test]
+    fn weekday_preconditions() {
+        // The clock starts on the first day of the week
+        let time = SimulationTime::default();
+        let calendar = Calendar::default();
+        let motives = BasicMotives::default();
+
+        let mut work = bed();
+        work.preconditions = vec![
+            Precondition::OnWeekdays(
+                ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday"]
+                    .iter()
+                    .map(|day| day.to_string())
+                    .collect(),
+            ),
+            Precondition::BetweenHours(9, 17),
+        ];
+        assert!(work.is_available(&motives, &time, &calendar));
+
+        let mut party = bed();
+        party.preconditions = vec![Precondition::OnWeekdays(vec![String::from("Saturday")])];
+        assert!(!party.is_available(&motives, &time, &calendar));
+    }
 }
@@ -1,7 +1,577 @@
-use bevy::ecs::component::Component;
+use std::collections::HashMap;
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct TilePosition(bevy::math::IVec2);
+use bevy::{
+    math::{IVec2, UVec2, Vec2},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
 
+use crate::{
+    object::InteractableObject,
+    time::{advance_time, run_simulation_ticks, SimulationClock, SimulationTick},
+};
+
+// One tile is one world unit, and tile centers sit on whole world coordinates
+pub const PIXELS_PER_WORLD_UNIT: f32 = 64.0;
+
+const DEFAULT_GRID_ORIGIN: IVec2 = IVec2::new(-12, -6);
+const DEFAULT_GRID_SIZE: UVec2 = UVec2::new(24, 12);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TilePosition(pub IVec2);
+
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct WorldPosition(pub Vec2);
+
+// Where something was before the last tick, for drawing it part way to where it is now
 #[derive(Component, Clone, Debug, PartialEq)]
-pub struct WorldPosition(pub bevy::math::Vec2);
+pub struct PreviousWorldPosition(pub Vec2);
+
+impl TilePosition {
+    pub fn new(x: i32, y: i32) -> Self {
+        TilePosition(IVec2::new(x, y))
+    }
+
+    // The tile the position is in
+    pub fn from_world(position: &WorldPosition) -> Self {
+        TilePosition(position.0.round().as_ivec2())
+    }
+
+    pub fn center(&self) -> WorldPosition {
+        WorldPosition(self.0.as_vec2())
+    }
+}
+
+impl WorldPosition {
+    // Position in the 2D scene, in pixels
+    pub fn to_screen(&self) -> Vec2 {
+        self.0 * PIXELS_PER_WORLD_UNIT
+    }
+
+    pub fn from_screen(screen_position: Vec2) -> Self {
+        WorldPosition(screen_position / PIXELS_PER_WORLD_UNIT)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum Floor {
+    #[default]
+    Grass,
+    Wood,
+    Tiles,
+    Carpet,
+}
+
+impl Floor {
+    // How much nicer a room gets for every tile of this floor, see environment_score
+    pub fn environment(&self) -> f32 {
+        match self {
+            Floor::Grass => 0.0,
+            Floor::Wood => 0.05,
+            Floor::Tiles => 0.03,
+            Floor::Carpet => 0.1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Tile {
+    pub floor: Floor,
+    // Object standing on this tile, if any
+    pub occupant: Option<Entity>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub fn offset(&self) -> IVec2 {
+        match self {
+            Direction::North => IVec2::new(0, 1),
+            Direction::East => IVec2::new(1, 0),
+            Direction::South => IVec2::new(0, -1),
+            Direction::West => IVec2::new(-1, 0),
+        }
+    }
+}
+
+// Objects can be turned in steps of 90 degrees, counterclockwise
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Rotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl Rotation {
+    pub fn next(&self) -> Rotation {
+        match self {
+            Rotation::None => Rotation::Quarter,
+            Rotation::Quarter => Rotation::Half,
+            Rotation::Half => Rotation::ThreeQuarters,
+            Rotation::ThreeQuarters => Rotation::None,
+        }
+    }
+
+    pub fn radians(&self) -> f32 {
+        match self {
+            Rotation::None => 0.0,
+            Rotation::Quarter => std::f32::consts::FRAC_PI_2,
+            Rotation::Half => std::f32::consts::PI,
+            Rotation::ThreeQuarters => 3.0 * std::f32::consts::FRAC_PI_2,
+        }
+    }
+
+    pub fn rotate_tile_offset(&self, offset: IVec2) -> IVec2 {
+        match self {
+            Rotation::None => offset,
+            Rotation::Quarter => IVec2::new(-offset.y, offset.x),
+            Rotation::Half => -offset,
+            Rotation::ThreeQuarters => IVec2::new(offset.y, -offset.x),
+        }
+    }
+
+    pub fn rotate_offset(&self, offset: Vec2) -> Vec2 {
+        match self {
+            Rotation::None => offset,
+            Rotation::Quarter => Vec2::new(-offset.y, offset.x),
+            Rotation::Half => -offset,
+            Rotation::ThreeQuarters => Vec2::new(offset.y, -offset.x),
+        }
+    }
+}
+
+// What's on the edge between two tiles. Edges without anything on them are open.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum Edge {
+    Wall,
+    // Lets fellas through, but still separates rooms
+    Door,
+}
+
+// Every edge is stored as the south or west edge of some tile,
+// so that both tiles next to it agree on what's there
+fn normalize_edge(position: TilePosition, side: Direction) -> (TilePosition, Direction) {
+    match side {
+        Direction::North => (TilePosition(position.0 + IVec2::Y), Direction::South),
+        Direction::East => (TilePosition(position.0 + IVec2::X), Direction::West),
+        Direction::South | Direction::West => (position, side),
+    }
+}
+
+// Fixed size tile map of the whole lot
+#[derive(Resource, Clone, Debug)]
+pub struct WorldGrid {
+    // Tile position of the bottom left corner
+    origin: IVec2,
+    size: UVec2,
+    tiles: Vec<Tile>,
+    edges: HashMap<(TilePosition, Direction), Edge>,
+    // Bumped whenever a wall or door changes, so rooms know when to update
+    edge_generation: u32,
+}
+
+impl Default for WorldGrid {
+    fn default() -> Self {
+        WorldGrid::new(DEFAULT_GRID_ORIGIN, DEFAULT_GRID_SIZE)
+    }
+}
+
+impl WorldGrid {
+    pub fn new(origin: IVec2, size: UVec2) -> Self {
+        WorldGrid {
+            origin,
+            size,
+            tiles: vec![Tile::default(); (size.x * size.y) as usize],
+            edges: HashMap::new(),
+            edge_generation: 0,
+        }
+    }
+
+    fn index(&self, position: TilePosition) -> Option<usize> {
+        let local = position.0 - self.origin;
+
+        if local.x < 0 || local.y < 0 || local.x >= self.size.x as i32 {
+            return None;
+        }
+
+        if local.y >= self.size.y as i32 {
+            return None;
+        }
+
+        Some((local.y as u32 * self.size.x + local.x as u32) as usize)
+    }
+
+    pub fn contains(&self, position: TilePosition) -> bool {
+        self.index(position).is_some()
+    }
+
+    pub fn get(&self, position: TilePosition) -> Option<&Tile> {
+        self.index(position).map(|index| &self.tiles[index])
+    }
+
+    pub fn get_mut(&mut self, position: TilePosition) -> Option<&mut Tile> {
+        self.index(position).map(|index| &mut self.tiles[index])
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = TilePosition> + '_ {
+        (0..self.size.y as i32).flat_map(move |y| {
+            (0..self.size.x as i32).map(move |x| TilePosition(self.origin + IVec2::new(x, y)))
+        })
+    }
+
+    pub fn set_floor(&mut self, position: TilePosition, floor: Floor) {
+        if let Some(tile) = self.get_mut(position) {
+            tile.floor = floor;
+        }
+    }
+
+    // Every tile back to the default floor
+    pub fn clear_floors(&mut self) {
+        for tile in self.tiles.iter_mut() {
+            tile.floor = Floor::default();
+        }
+    }
+
+    pub fn edge(&self, position: TilePosition, side: Direction) -> Option<Edge> {
+        self.edges.get(&normalize_edge(position, side)).copied()
+    }
+
+    pub fn set_edge(&mut self, position: TilePosition, side: Direction, edge: Option<Edge>) {
+        let key = normalize_edge(position, side);
+
+        let previous = match edge {
+            Some(edge) => self.edges.insert(key, edge),
+            None => self.edges.remove(&key),
+        };
+
+        if previous != edge {
+            self.edge_generation += 1;
+        }
+    }
+
+    // Puts walls along the tile edges between two tile corners. The corner with
+    // the same coordinates as a tile is that tile's bottom left corner.
+    // Walls can only go straight along an axis.
+    pub fn add_wall(&mut self, from: IVec2, to: IVec2) {
+        if from.x == to.x {
+            for y in from.y.min(to.y)..from.y.max(to.y) {
+                self.set_edge(
+                    TilePosition::new(from.x, y),
+                    Direction::West,
+                    Some(Edge::Wall),
+                );
+            }
+        } else if from.y == to.y {
+            for x in from.x.min(to.x)..from.x.max(to.x) {
+                self.set_edge(
+                    TilePosition::new(x, from.y),
+                    Direction::South,
+                    Some(Edge::Wall),
+                );
+            }
+        } else {
+            println!("Walls have to be straight: {:?} to {:?}", from, to);
+        }
+    }
+
+    pub fn clear_edges(&mut self) {
+        if !self.edges.is_empty() {
+            self.edges.clear();
+            self.edge_generation += 1;
+        }
+    }
+
+    // Every wall and door, as the south or west edge of a tile
+    pub fn edges(&self) -> impl Iterator<Item = (TilePosition, Direction, Edge)> + '_ {
+        self.edges
+            .iter()
+            .map(|(&(position, side), &edge)| (position, side, edge))
+    }
+
+    pub fn edge_generation(&self) -> u32 {
+        self.edge_generation
+    }
+
+    // Can a fella walk from the tile to the next one in the given direction
+    pub fn can_cross(&self, position: TilePosition, side: Direction) -> bool {
+        self.edge(position, side) != Some(Edge::Wall)
+    }
+
+    // Can a fella stand on the tile
+    pub fn is_walkable(&self, position: TilePosition) -> bool {
+        self.get(position)
+            .is_some_and(|tile| tile.occupant.is_none())
+    }
+
+    // Whether an object could go on the tile, if the given object was out of the way
+    pub fn is_free_for(&self, position: TilePosition, object: Option<Entity>) -> bool {
+        self.get(position)
+            .is_some_and(|tile| tile.occupant.is_none() || tile.occupant == object)
+    }
+
+    // Tiles covered by an object whose anchor tile is at the given position.
+    // Unrotated footprints extend right and up from the anchor.
+    pub fn footprint_tiles(
+        anchor: TilePosition,
+        footprint: UVec2,
+        rotation: Rotation,
+    ) -> Vec<TilePosition> {
+        let footprint = footprint.max(UVec2::ONE);
+
+        (0..footprint.y as i32)
+            .flat_map(|y| (0..footprint.x as i32).map(move |x| IVec2::new(x, y)))
+            .map(|offset| TilePosition(anchor.0 + rotation.rotate_tile_offset(offset)))
+            .collect()
+    }
+
+    pub fn occupy(&mut self, object: Entity, tiles: impl IntoIterator<Item = TilePosition>) {
+        for position in tiles {
+            if let Some(tile) = self.get_mut(position) {
+                tile.occupant = Some(object);
+            }
+        }
+    }
+
+    pub fn vacate(&mut self, object: Entity) {
+        for tile in self.tiles.iter_mut() {
+            if tile.occupant == Some(object) {
+                tile.occupant = None;
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn occupy_object_tiles(
+    mut grid: ResMut<WorldGrid>,
+    objects: Query<
+        (Entity, &InteractableObject, &WorldPosition),
+        Or<(Changed<InteractableObject>, Changed<WorldPosition>)>,
+    >,
+    mut removed_objects: RemovedComponents<InteractableObject>,
+) {
+    for object in removed_objects.read() {
+        grid.vacate(object);
+    }
+
+    // Objects that moved or turned around free up the tiles they were on
+    for (object, interactable, world_position) in objects.iter() {
+        grid.vacate(object);
+        grid.occupy(object, interactable.footprint_tiles(world_position));
+    }
+}
+
+// Sprite drawn for a wall or door, replaced whenever the walls change
+#[derive(Component)]
+struct EdgeSprite;
+
+const WALL_THICKNESS: f32 = 8.0;
+
+fn draw_edges(
+    mut commands: Commands,
+    grid: Res<WorldGrid>,
+    edge_sprites: Query<Entity, With<EdgeSprite>>,
+    mut generation: Local<Option<u32>>,
+) {
+    if *generation == Some(grid.edge_generation()) {
+        return;
+    }
+
+    *generation = Some(grid.edge_generation());
+
+    for entity in edge_sprites.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    for (position, side, edge) in grid.edges() {
+        let center = position.center().0 + side.offset().as_vec2() * 0.5;
+
+        let length = match edge {
+            Edge::Wall => PIXELS_PER_WORLD_UNIT + WALL_THICKNESS,
+            Edge::Door => PIXELS_PER_WORLD_UNIT * 0.75,
+        };
+
+        let size = match side {
+            Direction::North | Direction::South => Vec2::new(length, WALL_THICKNESS),
+            Direction::East | Direction::West => Vec2::new(WALL_THICKNESS, length),
+        };
+
+        let color = match edge {
+            Edge::Wall => Color::rgb(0.3, 0.3, 0.35),
+            Edge::Door => Color::rgb(0.55, 0.35, 0.2),
+        };
+
+        commands.spawn((
+            EdgeSprite,
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_translation(
+                    WorldPosition(center).to_screen().extend(0.5),
+                ),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn remember_positions(mut query: Query<(&WorldPosition, &mut PreviousWorldPosition)>) {
+    for (world_position, mut previous) in query.iter_mut() {
+        previous.0 = world_position.0;
+    }
+}
+
+// Ticks only come a few times a second, so anything that moves is drawn between where it
+// was and where it is, by how far along the next tick is
+fn world_position_to_transform(
+    clock: Res<SimulationClock>,
+    mut query: Query<(
+        &mut Transform,
+        &WorldPosition,
+        Option<&PreviousWorldPosition>,
+    )>,
+) {
+    for (mut transform, world_position, previous) in query.iter_mut() {
+        let position = match previous {
+            Some(previous) => WorldPosition(previous.0.lerp(world_position.0, clock.overstep())),
+            None => world_position.clone(),
+        };
+
+        transform.translation = position.to_screen().extend(0.0);
+    }
+}
+
+pub struct WorldPlugin;
+
+impl Plugin for WorldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldGrid>()
+            .add_systems(Update, occupy_object_tiles)
+            .add_systems(SimulationTick, remember_positions.before(advance_time));
+    }
+}
+
+pub struct WorldRenderPlugin;
+
+impl Plugin for WorldRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                draw_edges,
+                world_position_to_transform.after(run_simulation_ticks),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_positions_round_to_nearest_tile() {
+        let position = WorldPosition(Vec2::new(1.4, -2.6));
+        assert_eq!(
+            TilePosition::from_world(&position),
+            TilePosition::new(1, -3)
+        );
+        assert_eq!(TilePosition::new(1, -3).center().0, Vec2::new(1.0, -3.0));
+    }
+
+    #[test]
+    fn screen_conversion_round_trips() {
+        let position = WorldPosition(Vec2::new(2.5, -1.0));
+        let screen = position.to_screen();
+
+        assert_eq!(screen, Vec2::new(160.0, -64.0));
+        assert_eq!(WorldPosition::from_screen(screen), position);
+    }
+
+    #[test]
+    fn rotated_footprints_turn_around_the_anchor() {
+        let anchor = TilePosition::new(0, 0);
+        let footprint = UVec2::new(1, 2);
+
+        assert_eq!(
+            WorldGrid::footprint_tiles(anchor, footprint, Rotation::Quarter),
+            vec![TilePosition::new(0, 0), TilePosition::new(-1, 0)]
+        );
+        assert_eq!(
+            WorldGrid::footprint_tiles(anchor, footprint, Rotation::Half),
+            vec![TilePosition::new(0, 0), TilePosition::new(0, -1)]
+        );
+        assert_eq!(
+            Rotation::ThreeQuarters.rotate_offset(Vec2::new(0.0, -1.0)),
+            Vec2::new(-1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn tiles_outside_grid_are_not_walkable() {
+        let grid = WorldGrid::new(IVec2::new(-2, -2), UVec2::new(4, 4));
+
+        assert!(grid.is_walkable(TilePosition::new(-2, -2)));
+        assert!(grid.is_walkable(TilePosition::new(1, 1)));
+        assert!(!grid.is_walkable(TilePosition::new(2, 0)));
+        assert!(!grid.is_walkable(TilePosition::new(0, -3)));
+    }
+
+    #[test]
+    fn objects_occupy_their_footprint() {
+        let mut grid = WorldGrid::new(IVec2::ZERO, UVec2::new(4, 4));
+        let bed = Entity::from_raw(7);
+
+        let tiles =
+            WorldGrid::footprint_tiles(TilePosition::new(1, 1), UVec2::new(1, 2), Rotation::None);
+        grid.occupy(bed, tiles);
+
+        assert_eq!(
+            grid.get(TilePosition::new(1, 2)).unwrap().occupant,
+            Some(bed)
+        );
+        assert!(!grid.is_walkable(TilePosition::new(1, 1)));
+        assert!(grid.is_walkable(TilePosition::new(2, 1)));
+        assert!(grid.is_free_for(TilePosition::new(1, 1), Some(bed)));
+        assert!(!grid.is_free_for(TilePosition::new(1, 1), None));
+
+        grid.vacate(bed);
+        assert!(grid.is_walkable(TilePosition::new(1, 2)));
+    }
+
+    #[test]
+    fn walls_block_crossing_from_both_sides() {
+        let mut grid = WorldGrid::new(IVec2::ZERO, UVec2::new(4, 4));
+        grid.add_wall(IVec2::new(2, 0), IVec2::new(2, 2));
+
+        assert!(!grid.can_cross(TilePosition::new(1, 1), Direction::East));
+        assert!(!grid.can_cross(TilePosition::new(2, 1), Direction::West));
+        assert!(grid.can_cross(TilePosition::new(1, 2), Direction::East));
+        assert!(grid.is_walkable(TilePosition::new(2, 1)));
+    }
+
+    #[test]
+    fn doors_can_be_crossed() {
+        let mut grid = WorldGrid::new(IVec2::ZERO, UVec2::new(4, 4));
+        grid.add_wall(IVec2::new(0, 2), IVec2::new(4, 2));
+        grid.set_edge(TilePosition::new(1, 1), Direction::North, Some(Edge::Door));
+
+        assert_eq!(
+            grid.edge(TilePosition::new(1, 2), Direction::South),
+            Some(Edge::Door)
+        );
+        assert!(grid.can_cross(TilePosition::new(1, 1), Direction::North));
+        assert!(!grid.can_cross(TilePosition::new(2, 1), Direction::North));
+    }
+}